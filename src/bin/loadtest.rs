@@ -0,0 +1,105 @@
+use digmake::loadtest::{run, LoadTestOptions};
+use std::time::{Duration, Instant};
+
+// the loadtest adds these to the time it starts, which panics if they do not fit
+fn checked_duration(seconds: f64) -> Option<Duration> {
+    let duration = Duration::try_from_secs_f64(seconds).ok()?;
+    Instant::now().checked_add(duration).map(|_| duration)
+}
+
+// a number of seconds once multiplied by scale
+fn duration(value: &str, scale: f64) -> Result<Duration, String> {
+    let number: f64 = value
+        .parse()
+        .map_err(|_| format!("{} is not a number", value))?;
+    checked_duration(number * scale)
+        .ok_or_else(|| format!("{} is negative, too large or not a number", value))
+}
+
+// per second, 0 to not do it at all
+fn rate(value: &str) -> Result<f64, String> {
+    let number: f64 = value
+        .parse()
+        .map_err(|_| format!("{} is not a number", value))?;
+    // the time between each is 1 / rate
+    if !number.is_finite() || number != 0.0 && checked_duration(1.0 / number).is_none() {
+        return Err(format!("{} is negative, too small or not finite", value));
+    }
+    Ok(number)
+}
+
+fn main() {
+    use clap::{App, Arg};
+
+    let matches = App::new("digmake-loadtest")
+        .about("Connects many bots to an offline mode server and reports how it held up")
+        .arg(
+            Arg::with_name("address")
+                .default_value("127.0.0.1:25565")
+                .help("server to connect to"),
+        )
+        .arg(
+            Arg::with_name("bots")
+                .long("bots")
+                .short("n")
+                .takes_value(true)
+                .default_value("10")
+                .validator(|bots| bots.parse::<usize>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("how many bots to connect"),
+        )
+        .arg(
+            Arg::with_name("name")
+                .long("name")
+                .takes_value(true)
+                .default_value("bot")
+                .help("bots are named this followed by their number"),
+        )
+        .arg(
+            Arg::with_name("connect-interval")
+                .long("connect-interval")
+                .takes_value(true)
+                .default_value("50")
+                .validator(|ms| duration(&ms, 0.001).map(|_| ()))
+                .help("milliseconds between starting bots"),
+        )
+        .arg(
+            Arg::with_name("duration")
+                .long("duration")
+                .short("d")
+                .takes_value(true)
+                .default_value("60")
+                .validator(|seconds| duration(&seconds, 1.0).map(|_| ()))
+                .help("seconds each bot stays once logged in"),
+        )
+        .arg(
+            Arg::with_name("move-rate")
+                .long("move-rate")
+                .takes_value(true)
+                .default_value("20")
+                .validator(|value| rate(&value).map(|_| ()))
+                .help("moves per second for each bot, 0 to stand still"),
+        )
+        .arg(
+            Arg::with_name("chat-rate")
+                .long("chat-rate")
+                .takes_value(true)
+                .default_value("0.1")
+                .validator(|value| rate(&value).map(|_| ()))
+                .help("chat messages per second for each bot, 0 to stay quiet"),
+        )
+        .get_matches();
+
+    // all checked by the validators
+    let value = |name| matches.value_of(name).unwrap();
+    let options = LoadTestOptions {
+        address: value("address").to_owned(),
+        bots: value("bots").parse().unwrap(),
+        name_prefix: value("name").to_owned(),
+        connect_interval: duration(value("connect-interval"), 0.001).unwrap(),
+        duration: duration(value("duration"), 1.0).unwrap(),
+        move_rate: rate(value("move-rate")).unwrap(),
+        chat_rate: rate(value("chat-rate")).unwrap(),
+    };
+
+    run(&options).print();
+}
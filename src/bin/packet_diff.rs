@@ -0,0 +1,403 @@
+// Compares two recorded connections packet by packet, to see what a server update changed
+//
+// Each direction is aligned on its own, by packet type (state and id), with a longest common
+// subsequence. Packets only in the first capture are removed, packets only in the second are added,
+// and a removed and an added packet of the same type count as reordered. Packets that line up are
+// compared field by field, using the packets decoded by the inspector rather than their bytes.
+
+use digmake::inspector::{read_data, read_records, Format, PacketRecord};
+use digmake::logic::State;
+use serde_json::Value;
+
+fn key(record: &PacketRecord) -> (State, i32) {
+    (record.state, record.id)
+}
+
+fn describe(record: &PacketRecord) -> String {
+    format!(
+        "{:?} 0x{:02X} {}",
+        record.state,
+        record.id,
+        record.name.unwrap_or("unknown")
+    )
+}
+
+// the snake (a run of matching packets) in the middle of a shortest edit script of a and b,
+// as (x, y) to (u, v), from Myers' "An O(ND) Difference Algorithm and Its Variations"
+// the edits before and after it take about half of the script each
+fn middle_snake<T: PartialEq>(a: &[T], b: &[T]) -> (usize, usize, usize, usize) {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let delta = n - m;
+    let odd = delta % 2 != 0;
+    let max = (n + m + 1) / 2;
+
+    // furthest x reached on each diagonal k = x - y, going forward from the start,
+    // and going backward from the end (counted from the end)
+    let offset = max + 1;
+    let mut forward = vec![0isize; (2 * offset + 1) as usize];
+    let mut backward = vec![0isize; (2 * offset + 1) as usize];
+    let at = |k: isize| (k + offset) as usize;
+
+    for d in 0..=max {
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && forward[at(k - 1)] < forward[at(k + 1)]) {
+                forward[at(k + 1)]
+            } else {
+                forward[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            let (x0, y0) = (x, y);
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            forward[at(k)] = x;
+
+            // overlaps the backward path on the same diagonal
+            if odd && (delta - k).abs() < d && x + backward[at(delta - k)] >= n {
+                return (x0 as usize, y0 as usize, x as usize, y as usize);
+            }
+        }
+
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && backward[at(k - 1)] < backward[at(k + 1)]) {
+                backward[at(k + 1)]
+            } else {
+                backward[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            let (x0, y0) = (x, y);
+            while x < n && y < m && a[(n - 1 - x) as usize] == b[(m - 1 - y) as usize] {
+                x += 1;
+                y += 1;
+            }
+            backward[at(k)] = x;
+
+            if !odd && (delta - k).abs() <= d && x + forward[at(delta - k)] >= n {
+                let (x, y, x0, y0) = (n - x, m - y, n - x0, m - y0);
+                return (x as usize, y as usize, x0 as usize, y0 as usize);
+            }
+        }
+    }
+
+    unreachable!("the paths always meet by the time d reaches (n + m) / 2")
+}
+
+// adds the pairs of indices of matching packets, after adding start to them
+fn align_range<T: PartialEq>(
+    a: &[T],
+    b: &[T],
+    start: (usize, usize),
+    pairs: &mut Vec<(usize, usize)>,
+) {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let a_mid = &a[prefix..a.len() - suffix];
+    let b_mid = &b[prefix..b.len() - suffix];
+    let (a_start, b_start) = (start.0 + prefix, start.1 + prefix);
+
+    pairs.extend((0..prefix).map(|i| (start.0 + i, start.1 + i)));
+    if !a_mid.is_empty() && !b_mid.is_empty() {
+        let (x, y, u, v) = middle_snake(a_mid, b_mid);
+        align_range(&a_mid[..x], &b_mid[..y], (a_start, b_start), pairs);
+        pairs.extend((0..u - x).map(|i| (a_start + x + i, b_start + y + i)));
+        align_range(&a_mid[u..], &b_mid[v..], (a_start + u, b_start + v), pairs);
+    }
+    pairs.extend((0..suffix).map(|i| (a_start + a_mid.len() + i, b_start + b_mid.len() + i)));
+}
+
+// indices into a and b of the packets in a longest common subsequence of packet types
+// found in linear space, since captures can have hundreds of thousands of packets
+fn align(a: &[&PacketRecord], b: &[&PacketRecord]) -> Vec<(usize, usize)> {
+    let a: Vec<_> = a.iter().map(|record| key(record)).collect();
+    let b: Vec<_> = b.iter().map(|record| key(record)).collect();
+
+    let mut pairs = Vec::new();
+    align_range(&a, &b, (0, 0), &mut pairs);
+    pairs
+}
+
+// long values like chunk data are cut short
+fn show_value(value: &Value) -> String {
+    const MAX_LEN: usize = 80;
+
+    let text = value.to_string();
+    if text.chars().count() > MAX_LEN {
+        let short: String = text.chars().take(MAX_LEN).collect();
+        format!("{}...", short)
+    } else {
+        text
+    }
+}
+
+// one line for each field that differs, with its path from the top of the packet
+fn diff_values(path: &str, a: &Value, b: &Value, output: &mut Vec<String>) {
+    let path_or_value = || {
+        if path.is_empty() {
+            String::from("(value)")
+        } else {
+            path.to_owned()
+        }
+    };
+
+    match (a, b) {
+        (Value::Object(a_fields), Value::Object(b_fields)) => {
+            for (name, a_value) in a_fields {
+                let field_path = format!("{}.{}", path, name);
+                match b_fields.get(name) {
+                    Some(b_value) => diff_values(&field_path, a_value, b_value, output),
+                    None => output.push(format!(
+                        "{}: {} -> (missing)",
+                        field_path,
+                        show_value(a_value)
+                    )),
+                }
+            }
+            for (name, b_value) in b_fields {
+                if !a_fields.contains_key(name) {
+                    output.push(format!(
+                        "{}.{}: (missing) -> {}",
+                        path,
+                        name,
+                        show_value(b_value)
+                    ));
+                }
+            }
+        }
+        (Value::Array(a_items), Value::Array(b_items)) => {
+            if a_items.len() != b_items.len() {
+                output.push(format!(
+                    "{}: {} items -> {} items",
+                    path_or_value(),
+                    a_items.len(),
+                    b_items.len()
+                ));
+            }
+            for (i, (a_item, b_item)) in a_items.iter().zip(b_items).enumerate() {
+                diff_values(&format!("{}[{}]", path, i), a_item, b_item, output);
+            }
+        }
+        _ if a != b => output.push(format!(
+            "{}: {} -> {}",
+            path_or_value(),
+            show_value(a),
+            show_value(b)
+        )),
+        _ => {}
+    }
+}
+
+fn diff_records(a: &PacketRecord, b: &PacketRecord) -> Vec<String> {
+    let mut output = Vec::new();
+
+    if a.error != b.error {
+        let error =
+            |record: &PacketRecord| record.error.clone().unwrap_or_else(|| String::from("none"));
+        output.push(format!("error: {} -> {}", error(a), error(b)));
+    }
+    match (&a.fields, &b.fields) {
+        (Some(a_fields), Some(b_fields)) => diff_values("", a_fields, b_fields, &mut output),
+        (None, None) => {}
+        (a_fields, b_fields) => output.push(format!(
+            "decoded: {} -> {}",
+            a_fields.is_some(),
+            b_fields.is_some()
+        )),
+    }
+
+    output
+}
+
+// the packets sent by one side, without the ignored packet types
+fn select<'a>(
+    records: &'a [PacketRecord],
+    direction: &str,
+    ignored: &[&str],
+) -> Vec<&'a PacketRecord> {
+    let is_ignored = |record: &PacketRecord| match record.name {
+        Some(name) => ignored
+            .iter()
+            .any(|ignored| ignored.eq_ignore_ascii_case(name)),
+        None => false,
+    };
+
+    records
+        .iter()
+        .filter(|record| record.direction == direction && !is_ignored(record))
+        .collect()
+}
+
+// prints the differences in one direction, returns whether there were any
+fn diff_direction(
+    direction: &str,
+    a: &[&PacketRecord],
+    b: &[&PacketRecord],
+    max_field_diffs: usize,
+) -> bool {
+    let pairs = align(a, b);
+
+    let mut removed: Vec<Option<&PacketRecord>> = a.iter().copied().map(Some).collect();
+    let mut added: Vec<Option<&PacketRecord>> = b.iter().copied().map(Some).collect();
+    for &(i, j) in &pairs {
+        removed[i] = None;
+        added[j] = None;
+    }
+
+    // a packet type that was taken out in one place and put back in another
+    let mut reordered = Vec::new();
+    for slot in removed.iter_mut() {
+        let a_record = match slot {
+            Some(record) => *record,
+            None => continue,
+        };
+        let other = added
+            .iter_mut()
+            .find(|other| matches!(other, Some(b_record) if key(b_record) == key(a_record)));
+        if let Some(other) = other {
+            reordered.push((a_record, other.take().unwrap()));
+            *slot = None;
+        }
+    }
+
+    let show_fields = |lines: Vec<String>| {
+        for line in lines.iter().take(max_field_diffs) {
+            println!("      {}", line);
+        }
+        if lines.len() > max_field_diffs {
+            println!("      ... and {} more", lines.len() - max_field_diffs);
+        }
+    };
+
+    let mut changed = 0;
+    println!(
+        "== sent by {}: {} packets -> {} packets ==",
+        direction,
+        a.len(),
+        b.len()
+    );
+    for &(i, j) in &pairs {
+        let lines = diff_records(a[i], b[j]);
+        if !lines.is_empty() {
+            changed += 1;
+            println!("  ! #{} -> #{} {}", a[i].index, b[j].index, describe(a[i]));
+            show_fields(lines);
+        }
+    }
+    for (a_record, b_record) in &reordered {
+        println!(
+            "  ~ #{} -> #{} {} (reordered)",
+            a_record.index,
+            b_record.index,
+            describe(a_record)
+        );
+        show_fields(diff_records(a_record, b_record));
+    }
+    for record in removed.iter().flatten() {
+        println!("  - #{} {}", record.index, describe(record));
+    }
+    for record in added.iter().flatten() {
+        println!("  + #{} {}", record.index, describe(record));
+    }
+
+    let removed = removed.iter().flatten().count();
+    let added = added.iter().flatten().count();
+    println!(
+        "  {} matched, {} changed, {} reordered, {} removed, {} added",
+        pairs.len(),
+        changed,
+        reordered.len(),
+        removed,
+        added
+    );
+
+    changed + reordered.len() + removed + added > 0
+}
+
+fn main() {
+    use clap::{App, Arg};
+
+    let matches = App::new("packet_diff")
+        .about("Compares the packets of two recorded minecraft connections")
+        .arg(
+            Arg::with_name("old")
+                .required(true)
+                .help("the connection to compare against"),
+        )
+        .arg(
+            Arg::with_name("new")
+                .required(true)
+                .help("the connection to compare"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .short("f")
+                .takes_value(true)
+                .possible_values(&["auto", "yaml", "capture", "pcap"])
+                .default_value("auto")
+                .help("input format of both files, auto picks it from each file extension"),
+        )
+        .arg(
+            Arg::with_name("port")
+                .long("port")
+                .takes_value(true)
+                .validator(|port| port.parse::<u16>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("server port, for pcap input (guessed by default)"),
+        )
+        .arg(
+            Arg::with_name("ignore")
+                .long("ignore")
+                .short("i")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("leave out packets with this name (like KeepAlive), can be repeated"),
+        )
+        .arg(
+            Arg::with_name("max-field-diffs")
+                .long("max-field-diffs")
+                .takes_value(true)
+                .default_value("10")
+                .validator(|max| max.parse::<usize>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("how many differing fields to show for each packet"),
+        )
+        .get_matches();
+
+    let format = Format::from_name(matches.value_of("format").unwrap());
+    let port = matches.value_of("port").map(|port| port.parse().unwrap());
+    let ignored: Vec<&str> = matches.values_of("ignore").into_iter().flatten().collect();
+    let max_field_diffs = matches
+        .value_of("max-field-diffs")
+        .unwrap()
+        .parse()
+        .unwrap();
+
+    let read = |filename: &str| {
+        let format = format.unwrap_or_else(|| Format::from_path(filename));
+        let records = read_data(filename, format, port).and_then(|data| read_records(&data));
+        records.unwrap_or_else(|e| {
+            // like diff, exit with 2 if something went wrong
+            eprintln!("{}: {}", filename, e);
+            std::process::exit(2)
+        })
+    };
+    let old = read(matches.value_of("old").unwrap());
+    let new = read(matches.value_of("new").unwrap());
+
+    let mut differs = false;
+    for direction in &["client", "server"] {
+        let old = select(&old, direction, &ignored);
+        let new = select(&new, direction, &ignored);
+        differs |= diff_direction(direction, &old, &new, max_field_diffs);
+    }
+
+    // like diff, exit with 1 if the connections differ
+    if differs {
+        std::process::exit(1);
+    }
+}
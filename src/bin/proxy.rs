@@ -0,0 +1,87 @@
+use digmake::inspector::{Mode, Options};
+use digmake::proxy::{run, DropPackets, HookFactory, Lag, ProxyOptions};
+use std::time::Duration;
+
+fn main() {
+    use clap::{App, Arg};
+
+    let matches = App::new("digmake-proxy")
+        .about("Forwards minecraft connections to a server, decoding and recording their packets")
+        .arg(
+            Arg::with_name("upstream")
+                .required(true)
+                .help("address of the server to forward to, like localhost:25565"),
+        )
+        .arg(
+            Arg::with_name("listen")
+                .long("listen")
+                .short("l")
+                .takes_value(true)
+                .default_value("127.0.0.1:25566")
+                .help("address to accept clients on"),
+        )
+        .arg(
+            Arg::with_name("log-dir")
+                .long("log-dir")
+                .takes_value(true)
+                .help(
+                    "directory for a capture of each session, and its shared secret if encrypted",
+                ),
+        )
+        .arg(
+            Arg::with_name("mode")
+                .long("mode")
+                .short("m")
+                .takes_value(true)
+                .possible_values(&["none", "summary", "full", "hexdump", "json"])
+                .default_value("summary")
+                .help("what to print for each packet"),
+        )
+        .arg(
+            Arg::with_name("lag")
+                .long("lag")
+                .takes_value(true)
+                .validator(|lag| lag.parse::<u64>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("milliseconds to delay each packet in the play state by, each way"),
+        )
+        .arg(
+            Arg::with_name("drop")
+                .long("drop")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("drop packets with this name (like ChunkData), can be repeated"),
+        )
+        .get_matches();
+
+    let mode = match matches.value_of("mode").unwrap() {
+        "none" => None,
+        "full" => Some(Mode::Full),
+        "hexdump" => Some(Mode::Hexdump),
+        "json" => Some(Mode::Json),
+        _ => Some(Mode::Summary),
+    };
+
+    let mut hooks: Vec<HookFactory> = Vec::new();
+    if let Some(lag) = matches.value_of("lag") {
+        let lag = Duration::from_millis(lag.parse().unwrap());
+        hooks.push(Box::new(move || Box::new(Lag(lag))));
+    }
+    if let Some(names) = matches.values_of("drop") {
+        let names: Vec<String> = names.map(String::from).collect();
+        hooks.push(Box::new(move || Box::new(DropPackets(names.clone()))));
+    }
+
+    let options = ProxyOptions {
+        listen: matches.value_of("listen").unwrap().to_owned(),
+        upstream: matches.value_of("upstream").unwrap().to_owned(),
+        log_dir: matches.value_of("log-dir").map(Into::into),
+        inspector: mode.map(|mode| Options {
+            mode,
+            ..Options::default()
+        }),
+        hooks,
+    };
+
+    run(options).unwrap();
+}
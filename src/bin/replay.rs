@@ -0,0 +1,39 @@
+use digmake::replay::{run, Recording, ReplayOptions};
+use std::fs::File;
+use std::io::BufReader;
+
+fn main() {
+    use clap::{App, Arg};
+
+    let matches = App::new("digmake-replay")
+        .about("Plays a recorded connection back to a minecraft client")
+        .arg(
+            Arg::with_name("capture")
+                .required(true)
+                .help("capture to replay, like the ones digmake-proxy records"),
+        )
+        .arg(
+            Arg::with_name("listen")
+                .long("listen")
+                .short("l")
+                .takes_value(true)
+                .default_value("127.0.0.1:25565")
+                .help("address to accept clients on"),
+        )
+        .arg(
+            Arg::with_name("log-input")
+                .long("log-input")
+                .help("print the packets sent by clients"),
+        )
+        .get_matches();
+
+    let file = File::open(matches.value_of("capture").unwrap()).unwrap();
+    let recording = Recording::read(BufReader::new(file)).unwrap();
+
+    let options = ReplayOptions {
+        listen: matches.value_of("listen").unwrap().to_owned(),
+        log_input: matches.is_present("log-input"),
+    };
+
+    run(recording, options).unwrap();
+}
@@ -0,0 +1,166 @@
+// digmake's own packet capture format, so sessions can be recorded and replayed without Wireshark
+//
+// A capture file is the header followed by records until the end of the file, all encoded with se.
+// Each record holds one packet as it was after decompression and decryption: the packet id and
+// body, without the length prefix.
+
+use crate::logic::{PacketDirection, State};
+use crate::se::{self, Error, Result, VarInt};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Write};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+pub const MAGIC: [u8; 8] = *b"DIGMAKE\0";
+pub const VERSION: u8 = 1;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Header {
+    pub magic: [u8; 8],
+    pub version: u8,
+    // unix time in milliseconds of when the capture was started
+    pub started: u64,
+    // protocol version of the captured connection, or -1 if not known
+    pub protocol_version: VarInt,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    // microseconds since the capture was started
+    pub micros: u64,
+    pub direction: PacketDirection,
+    // the state of the connection when the packet was sent
+    pub state: State,
+    pub frame: Vec<u8>,
+}
+
+impl Record {
+    pub fn timestamp(&self) -> Duration {
+        Duration::from_micros(self.micros)
+    }
+
+    pub fn packet_id(&self) -> Result<i32> {
+        VarInt::_parse(&mut &self.frame[..])
+    }
+
+    // the frame without the packet id
+    pub fn body(&self) -> Result<&[u8]> {
+        let id_len = VarInt(self.packet_id()?).encoded_len();
+        Ok(&self.frame[id_len..])
+    }
+
+    // the frame with its length prefix, as it would be sent without compression
+    pub fn to_packet(&self) -> Result<Vec<u8>> {
+        let mut output = se::serialize(VarInt::from_usize(self.frame.len())?)?;
+        output.extend_from_slice(&self.frame);
+        Ok(output)
+    }
+}
+
+pub struct CaptureWriter<W> {
+    writer: W,
+    start: Instant,
+}
+
+impl<W: Write> CaptureWriter<W> {
+    pub fn new(mut writer: W, protocol_version: Option<i32>) -> Result<Self> {
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_millis() as u64)
+            .unwrap_or(0);
+
+        let header = Header {
+            magic: MAGIC,
+            version: VERSION,
+            started,
+            protocol_version: VarInt(protocol_version.unwrap_or(-1)),
+        };
+        se::serialize_to_writer(&mut writer, &header)?;
+
+        Ok(Self {
+            writer,
+            start: Instant::now(),
+        })
+    }
+
+    // records a frame (packet id and body) with the current time
+    pub fn write(&mut self, direction: PacketDirection, state: State, frame: &[u8]) -> Result<()> {
+        let record = Record {
+            micros: self.start.elapsed().as_micros() as u64,
+            direction,
+            state,
+            frame: frame.to_vec(),
+        };
+        self.write_record(&record)
+    }
+
+    // serializes the packet into a frame and records it
+    pub fn write_packet<T: Serialize>(
+        &mut self,
+        direction: PacketDirection,
+        state: State,
+        packet_id: i32,
+        data: &T,
+    ) -> Result<()> {
+        let mut frame = se::serialize(VarInt(packet_id))?;
+        se::serialize_into(&mut frame, data)?;
+        self.write(direction, state, &frame)
+    }
+
+    // keeps the timestamp in the record, for copying from another capture
+    pub fn write_record(&mut self, record: &Record) -> Result<()> {
+        se::serialize_to_writer(&mut self.writer, record)
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+pub struct CaptureReader<R> {
+    reader: R,
+    header: Header,
+}
+
+impl<R: BufRead> CaptureReader<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let header: Header = se::from_reader(&mut reader)?;
+
+        if header.magic != MAGIC {
+            return Err(Error::Other(String::from("not a digmake capture file")));
+        }
+        if header.version != VERSION {
+            return Err(Error::Other(format!(
+                "capture file version {} is not supported, expected {}",
+                header.version, VERSION
+            )));
+        }
+
+        Ok(Self { reader, header })
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    // None at the end of the file
+    pub fn read_record(&mut self) -> Result<Option<Record>> {
+        if self.reader.fill_buf()?.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(se::from_reader(&mut self.reader)?))
+    }
+}
+
+impl<R: BufRead> Iterator for CaptureReader<R> {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
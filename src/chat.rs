@@ -0,0 +1,486 @@
+// Chat text components, the JSON format used for chat messages, titles, display names and the
+// server list description.
+// In the protocol these are a JSON string, so when serialized by se (which is not human
+// readable) they are encoded to JSON first. Human readable formats get the structure directly.
+
+use serde::{de, ser, Deserialize, Serialize};
+use std::fmt::{self, Write};
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(remote = "Self")]
+pub struct TextComponent {
+    #[serde(flatten)]
+    pub content: Content,
+    #[serde(flatten)]
+    pub style: Style,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra: Vec<TextComponent>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Content {
+    Text {
+        text: String,
+    },
+    Translate {
+        translate: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        with: Vec<TextComponent>,
+    },
+    Score {
+        score: Score,
+    },
+    Selector {
+        selector: String,
+    },
+    Keybind {
+        keybind: String,
+    },
+}
+
+impl Default for Content {
+    fn default() -> Self {
+        Self::Text {
+            text: String::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Score {
+    pub name: String,
+    pub objective: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+}
+
+// Everything is optional, and unset values are inherited from the parent component
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Style {
+    // one of the 16 named colors, or #rrggbb since 1.16
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bold: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub italic: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub underlined: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strikethrough: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub obfuscated: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub insertion: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub click_event: Option<ClickEvent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hover_event: Option<HoverEvent>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ClickEvent {
+    pub action: ClickAction,
+    pub value: String,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClickAction {
+    OpenUrl,
+    OpenFile,
+    RunCommand,
+    SuggestCommand,
+    ChangePage,
+    CopyToClipboard,
+}
+
+// contents depends on the action (a component, an item or an entity) so it is left as JSON
+// value is the pre-1.16 name for contents
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HoverEvent {
+    pub action: HoverAction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contents: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<serde_json::Value>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HoverAction {
+    ShowText,
+    ShowItem,
+    ShowEntity,
+}
+
+// name, legacy code, ANSI color, RGB
+const COLORS: [(&str, char, u8, u32); 16] = [
+    ("black", '0', 30, 0x000000),
+    ("dark_blue", '1', 34, 0x0000AA),
+    ("dark_green", '2', 32, 0x00AA00),
+    ("dark_aqua", '3', 36, 0x00AAAA),
+    ("dark_red", '4', 31, 0xAA0000),
+    ("dark_purple", '5', 35, 0xAA00AA),
+    ("gold", '6', 33, 0xFFAA00),
+    ("gray", '7', 37, 0xAAAAAA),
+    ("dark_gray", '8', 90, 0x555555),
+    ("blue", '9', 94, 0x5555FF),
+    ("green", 'a', 92, 0x55FF55),
+    ("aqua", 'b', 96, 0x55FFFF),
+    ("red", 'c', 91, 0xFF5555),
+    ("light_purple", 'd', 95, 0xFF55FF),
+    ("yellow", 'e', 93, 0xFFFF55),
+    ("white", 'f', 97, 0xFFFFFF),
+];
+
+fn parse_hex_color(color: &str) -> Option<u32> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok()
+}
+
+// legacy codes only have the 16 named colors, so hex colors use the closest one
+fn legacy_color(color: &str) -> Option<char> {
+    if let Some(&(_, code, _, _)) = COLORS.iter().find(|(name, ..)| *name == color) {
+        return Some(code);
+    }
+
+    let rgb = parse_hex_color(color)?;
+    let channels = |rgb: u32| [(rgb >> 16) & 0xFF, (rgb >> 8) & 0xFF, rgb & 0xFF];
+    let distance = |other: u32| -> i64 {
+        channels(rgb)
+            .iter()
+            .zip(channels(other).iter())
+            .map(|(&a, &b)| (a as i64 - b as i64).pow(2))
+            .sum()
+    };
+
+    COLORS
+        .iter()
+        .min_by_key(|(.., other)| distance(*other))
+        .map(|&(_, code, ..)| code)
+}
+
+fn ansi_color(color: &str) -> Option<String> {
+    if let Some(&(_, _, ansi, _)) = COLORS.iter().find(|(name, ..)| *name == color) {
+        return Some(ansi.to_string());
+    }
+
+    let rgb = parse_hex_color(color)?;
+    Some(format!(
+        "38;2;{};{};{}",
+        (rgb >> 16) & 0xFF,
+        (rgb >> 8) & 0xFF,
+        rgb & 0xFF
+    ))
+}
+
+impl Style {
+    // fill in anything unset from the parent
+    fn inherit(&self, parent: &Style) -> Style {
+        Style {
+            color: self.color.clone().or_else(|| parent.color.clone()),
+            bold: self.bold.or(parent.bold),
+            italic: self.italic.or(parent.italic),
+            underlined: self.underlined.or(parent.underlined),
+            strikethrough: self.strikethrough.or(parent.strikethrough),
+            obfuscated: self.obfuscated.or(parent.obfuscated),
+            font: self.font.clone().or_else(|| parent.font.clone()),
+            insertion: self.insertion.clone().or_else(|| parent.insertion.clone()),
+            click_event: self
+                .click_event
+                .clone()
+                .or_else(|| parent.click_event.clone()),
+            hover_event: self
+                .hover_event
+                .clone()
+                .or_else(|| parent.hover_event.clone()),
+        }
+    }
+
+    // bold, italic, underlined, strikethrough, obfuscated
+    fn formats(&self) -> [bool; 5] {
+        [
+            self.bold.unwrap_or(false),
+            self.italic.unwrap_or(false),
+            self.underlined.unwrap_or(false),
+            self.strikethrough.unwrap_or(false),
+            self.obfuscated.unwrap_or(false),
+        ]
+    }
+}
+
+impl TextComponent {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            content: Content::Text { text: text.into() },
+            ..Default::default()
+        }
+    }
+
+    pub fn translate(key: impl Into<String>, with: Vec<TextComponent>) -> Self {
+        Self {
+            content: Content::Translate {
+                translate: key.into(),
+                with,
+            },
+            ..Default::default()
+        }
+    }
+
+    pub fn color(mut self, color: impl Into<String>) -> Self {
+        self.style.color = Some(color.into());
+        self
+    }
+
+    pub fn bold(mut self) -> Self {
+        self.style.bold = Some(true);
+        self
+    }
+
+    pub fn push(mut self, child: TextComponent) -> Self {
+        self.extra.push(child);
+        self
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    // Without the language files, translations are shown as their key followed by the arguments,
+    // like chat.type.text[Steve, hello]
+    fn own_text(&self) -> String {
+        match &self.content {
+            Content::Text { text } => text.clone(),
+            Content::Translate { translate, with } if with.is_empty() => translate.clone(),
+            Content::Translate { translate, with } => {
+                let args: Vec<String> = with.iter().map(|arg| arg.to_plain()).collect();
+                format!("{}[{}]", translate, args.join(", "))
+            }
+            Content::Score { score } => score.value.clone().unwrap_or_else(|| score.name.clone()),
+            Content::Selector { selector } => selector.clone(),
+            Content::Keybind { keybind } => keybind.clone(),
+        }
+    }
+
+    // this component and its children in order, with the style each one ends up with
+    fn walk<F: FnMut(String, &Style)>(&self, parent: &Style, f: &mut F) {
+        let style = self.style.inherit(parent);
+        f(self.own_text(), &style);
+        for child in &self.extra {
+            child.walk(&style, f);
+        }
+    }
+
+    // just the text, without any formatting
+    pub fn to_plain(&self) -> String {
+        let mut output = String::new();
+        self.walk(&Style::default(), &mut |text, _| output.push_str(&text));
+        output
+    }
+
+    // the old § formatting codes, as still understood by the server list and some plugins
+    pub fn to_legacy(&self) -> String {
+        const FORMAT_CODES: [char; 5] = ['l', 'o', 'n', 'm', 'k'];
+
+        let mut output = String::new();
+        let mut current: Option<(Option<char>, [bool; 5])> = None;
+
+        self.walk(&Style::default(), &mut |text, style| {
+            if text.is_empty() {
+                return;
+            }
+
+            let color = style.color.as_deref().and_then(legacy_color);
+            let formats = style.formats();
+            if current != Some((color, formats)) {
+                // a color code also clears the formatting, so it has to come first
+                if color.is_some() || current.is_some() {
+                    output.push('§');
+                    output.push(color.unwrap_or('r'));
+                }
+                for (&on, &code) in formats.iter().zip(FORMAT_CODES.iter()) {
+                    if on {
+                        output.push('§');
+                        output.push(code);
+                    }
+                }
+                current = Some((color, formats));
+            }
+
+            output.push_str(&text);
+        });
+
+        output
+    }
+
+    // colored with ANSI escape codes, for printing to a terminal
+    pub fn to_ansi(&self) -> String {
+        // obfuscated text is shown as reversed, since terminals cant scramble it
+        const FORMAT_CODES: [&str; 5] = ["1", "3", "4", "9", "7"];
+
+        let mut output = String::new();
+        self.walk(&Style::default(), &mut |text, style| {
+            if text.is_empty() {
+                return;
+            }
+
+            let mut codes = vec!["0".to_owned()];
+            codes.extend(style.color.as_deref().and_then(ansi_color));
+            for (&on, &code) in style.formats().iter().zip(FORMAT_CODES.iter()) {
+                if on {
+                    codes.push(code.to_owned());
+                }
+            }
+
+            let _ = write!(output, "\x1b[{}m{}", codes.join(";"), text);
+        });
+
+        if !output.is_empty() {
+            output.push_str("\x1b[0m");
+        }
+        output
+    }
+}
+
+impl From<&str> for TextComponent {
+    fn from(text: &str) -> Self {
+        Self::text(text)
+    }
+}
+
+impl fmt::Display for TextComponent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_plain())
+    }
+}
+
+impl Serialize for TextComponent {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        if !serializer.is_human_readable() {
+            return Json(self).serialize(serializer);
+        }
+
+        TextComponent::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TextComponent {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        if !deserializer.is_human_readable() {
+            return Json::deserialize(deserializer).map(|json: Json<Self>| json.0);
+        }
+
+        // a plain string is shorthand for a text component, and a list is the first element with
+        // the rest as its extra
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Text(String),
+            List(Vec<TextComponent>),
+            Object(#[serde(deserialize_with = "object")] Box<TextComponent>),
+        }
+
+        fn object<'de, D>(deserializer: D) -> Result<Box<TextComponent>, D::Error>
+        where
+            D: de::Deserializer<'de>,
+        {
+            TextComponent::deserialize(deserializer).map(Box::new)
+        }
+
+        match Repr::deserialize(deserializer)? {
+            Repr::Text(text) => Ok(Self::text(text)),
+            Repr::List(list) => {
+                let mut list = list.into_iter();
+                let mut first = list
+                    .next()
+                    .ok_or_else(|| de::Error::invalid_length(0, &"at least one component"))?;
+                first.extra.extend(list);
+                Ok(first)
+            }
+            Repr::Object(component) => Ok(*component),
+        }
+    }
+}
+
+// A value stored as a JSON string, such as the status response
+// Human readable formats get the value itself instead of a string containing it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Json<T>(pub T);
+
+impl<T: Serialize> Serialize for Json<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        if serializer.is_human_readable() {
+            return self.0.serialize(serializer);
+        }
+
+        let json = serde_json::to_string(&self.0).map_err(ser::Error::custom)?;
+        serializer.serialize_str(&json)
+    }
+}
+
+impl<'de, T: de::DeserializeOwned> Deserialize<'de> for Json<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        if deserializer.is_human_readable() {
+            return T::deserialize(deserializer).map(Json);
+        }
+
+        let json = String::deserialize(deserializer)?;
+        serde_json::from_str(&json)
+            .map(Json)
+            .map_err(de::Error::custom)
+    }
+}
+
+// The response to a server list ping
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StatusResponse {
+    pub version: StatusVersion,
+    pub players: StatusPlayers,
+    pub description: TextComponent,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub favicon: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StatusVersion {
+    pub name: String,
+    pub protocol: i32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StatusPlayers {
+    pub max: i32,
+    pub online: i32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sample: Vec<StatusPlayer>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StatusPlayer {
+    pub name: String,
+    pub id: uuid::Uuid,
+}
@@ -0,0 +1,540 @@
+// A headless client, for bots that load test or script a server
+//
+// Client::connect does the handshake and an offline mode login, then starts a thread that reads
+// everything the server sends. That thread answers keep alives and confirms teleports by itself,
+// keeps track of where the player is, and turns the packets into Events for whoever holds the
+// Client. Sending is done by whichever thread calls the methods, behind a mutex shared with the
+// reading thread. Packet ids are for protocol 754 (1.16.4).
+
+use crate::chat::{Json, TextComponent};
+use crate::codec::{split_packet_id, Decoder, Encoder};
+use crate::inspector::Stats;
+use crate::logic::State;
+use crate::se::{self, Error, Result, VarInt};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub const PROTOCOL_VERSION: i32 = 754;
+
+// the vanilla client and server both cut chat messages off here
+const MAX_CHAT_LEN: usize = 256;
+
+// how many events can wait for the holder of the Client, see Play::event
+const MAX_EVENTS: usize = 1024;
+
+#[derive(Serialize, Debug)]
+struct Handshake<'a> {
+    protocol_version: VarInt,
+    address: &'a str,
+    port: u16,
+    next_state: VarInt,
+}
+
+#[derive(Deserialize, Debug)]
+struct LoginSuccess {
+    uuid: se::Uuid,
+    username: String,
+}
+
+// the rest of the packet is the channel and its data, which nothing here understands
+#[derive(Deserialize, Debug)]
+struct LoginPluginRequest {
+    message_id: VarInt,
+}
+
+#[derive(Deserialize, Debug)]
+struct Disconnect {
+    reason: Json<TextComponent>,
+}
+
+#[derive(Deserialize, Debug)]
+struct PlayerPositionAndLook {
+    x: f64,
+    y: f64,
+    z: f64,
+    yaw: f32,
+    pitch: f32,
+    flags: u8,
+    teleport_id: VarInt,
+}
+
+#[derive(Serialize, Debug)]
+struct PlayerPositionAndRotation {
+    x: f64,
+    y: f64,
+    z: f64,
+    yaw: f32,
+    pitch: f32,
+    on_ground: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct ChatMessage {
+    message: Json<TextComponent>,
+    position: u8,
+    sender: se::Uuid,
+}
+
+#[derive(Deserialize, Debug)]
+struct UpdateLatency {
+    uuid: se::Uuid,
+    // in milliseconds
+    ping: VarInt,
+}
+
+// where the player is, and where they are looking
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Location {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    JoinGame {
+        entity_id: i32,
+    },
+    // the server moved the player, which has already been confirmed
+    Teleported(Location),
+    Chat {
+        message: Box<TextComponent>,
+        // 0 for chat, 1 for system messages, 2 for the action bar
+        position: u8,
+        sender: uuid::Uuid,
+    },
+    // has already been answered
+    KeepAlive(i64),
+    // from a keep alive coming in to the first packet read after it was answered. The server does
+    // not answer back, so that packet stands in for an echo: on a busy server it comes within a
+    // tick of the answer, on a quiet one it can take much longer
+    KeepAliveRoundTrip(Duration),
+    // the ping the server shows for this player in the tab list, a smoothed average of the keep
+    // alive round trips it has timed, sent about every 30 seconds
+    Latency(Duration),
+    // every other play packet, with the packet id
+    Packet(i32, Vec<u8>),
+    // the last event, with the reason the server gave, or what went wrong
+    Disconnected(String),
+}
+
+// the sending half of the connection
+struct Writer {
+    stream: TcpStream,
+    encoder: Encoder,
+    // the packets sent so far, once logged in
+    shared: Option<Arc<Mutex<Shared>>>,
+}
+
+impl Writer {
+    fn send<T: Serialize>(&mut self, packet_id: i32, packet: &T) -> Result<()> {
+        let mut frame = se::serialize(VarInt(packet_id))?;
+        let id_len = frame.len();
+        se::serialize_into(&mut frame, packet)?;
+        let output = self.encoder.encode(&frame)?;
+        self.stream.write_all(&output)?;
+
+        if let Some(shared) = &self.shared {
+            let len = frame.len() - id_len;
+            shared
+                .lock()
+                .unwrap()
+                .stats
+                .add(State::Play, false, packet_id, len);
+        }
+        Ok(())
+    }
+}
+
+struct Reader {
+    stream: TcpStream,
+    decoder: Decoder,
+    buffer: Vec<u8>,
+    // how many times the stream has been read, and when it last was
+    reads: u64,
+    read_at: Instant,
+}
+
+impl Reader {
+    fn next_frame(&mut self) -> Result<(i32, Vec<u8>)> {
+        loop {
+            if let Some(frame) = self.decoder.next_frame()? {
+                let (packet_id, body) = split_packet_id(&frame)?;
+                return Ok((packet_id, body.to_vec()));
+            }
+
+            let len = self.stream.read(&mut self.buffer)?;
+            self.reads += 1;
+            self.read_at = Instant::now();
+            if len == 0 {
+                Err(Error::Other(String::from(
+                    "connection closed by the server",
+                )))?;
+            }
+            self.decoder.push(&self.buffer[..len]);
+        }
+    }
+}
+
+// what the reading thread keeps track of, for the Client to look at
+#[derive(Default)]
+struct Shared {
+    location: Option<Location>,
+    stats: Stats,
+    connected: bool,
+}
+
+pub struct Client {
+    pub username: String,
+    pub uuid: uuid::Uuid,
+    writer: Arc<Mutex<Writer>>,
+    shared: Arc<Mutex<Shared>>,
+    events: Receiver<Event>,
+}
+
+impl Client {
+    // logs in to an offline mode server
+    pub fn connect<A: ToSocketAddrs>(address: A, username: &str) -> Result<Self> {
+        let stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+        let peer = stream.peer_addr()?;
+
+        let mut writer = Writer {
+            stream: stream.try_clone()?,
+            encoder: Encoder::new(),
+            shared: None,
+        };
+        let mut reader = Reader {
+            stream,
+            decoder: Decoder::new(),
+            buffer: vec![0; 1 << 16],
+            reads: 0,
+            read_at: Instant::now(),
+        };
+
+        let handshake = Handshake {
+            protocol_version: VarInt(PROTOCOL_VERSION),
+            address: &peer.ip().to_string(),
+            port: peer.port(),
+            next_state: VarInt(2),
+        };
+        writer.send(0x00, &handshake)?;
+        writer.send(0x00, &username)?;
+
+        let login_success: LoginSuccess = loop {
+            let (packet_id, body) = reader.next_frame()?;
+            match packet_id {
+                0x00 => {
+                    let packet: Disconnect = se::from_bytes(&body)?;
+                    Err(Error::Other(format!(
+                        "disconnected while logging in: {}",
+                        packet.reason.0.to_plain()
+                    )))?;
+                }
+                0x01 => Err(Error::Other(String::from(
+                    "the server is in online mode, which is not supported",
+                )))?,
+                0x02 => break se::from_bytes(&body)?,
+                0x03 => {
+                    let threshold: VarInt = se::from_bytes(&body)?;
+                    reader.decoder.set_compression(threshold.0);
+                    writer.encoder.set_compression(threshold.0);
+                }
+                // like the vanilla client, say no to every channel
+                0x04 => {
+                    let request: LoginPluginRequest = se::from_bytes(&body)?;
+                    writer.send(0x02, &(request.message_id, false))?;
+                }
+                _ => Err(Error::Packet(format!(
+                    "unexpected packet 0x{:02X} while logging in",
+                    packet_id
+                )))?,
+            }
+        };
+
+        let shared = Arc::new(Mutex::new(Shared {
+            connected: true,
+            ..Shared::default()
+        }));
+        writer.shared = Some(shared.clone());
+
+        let (events_sender, events) = sync_channel(MAX_EVENTS);
+        let client = Self {
+            username: login_success.username,
+            uuid: login_success.uuid.0,
+            writer: Arc::new(Mutex::new(writer)),
+            shared,
+            events,
+        };
+
+        let mut play = Play {
+            uuid: client.uuid,
+            writer: client.writer.clone(),
+            shared: client.shared.clone(),
+            events: events_sender,
+            read: (Instant::now(), 0),
+            keep_alive: None,
+        };
+        std::thread::spawn(move || play.run(reader));
+
+        Ok(client)
+    }
+
+    // the events, in the order the packets came in. These have to be read: Packet events are
+    // dropped while too many are waiting, but once the others fill it up the reading thread waits,
+    // and stops answering keep alives
+    pub fn events(&self) -> &Receiver<Event> {
+        &self.events
+    }
+
+    // None until the server has sent where the player spawned
+    pub fn location(&self) -> Option<Location> {
+        self.shared.lock().unwrap().location
+    }
+
+    // the packets sent and received so far
+    pub fn stats(&self) -> Stats {
+        self.shared.lock().unwrap().stats.clone()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.shared.lock().unwrap().connected
+    }
+
+    pub fn send<T: Serialize>(&self, packet_id: i32, packet: &T) -> Result<()> {
+        self.writer.lock().unwrap().send(packet_id, packet)
+    }
+
+    // messages starting with a / are commands
+    pub fn chat(&self, message: &str) -> Result<()> {
+        // the server counts the UTF-16 units of its Java strings, not bytes
+        let len = message.encode_utf16().count();
+        if len > MAX_CHAT_LEN {
+            return Err(Error::Other(format!(
+                "chat message is {} characters long, the maximum is {}",
+                len, MAX_CHAT_LEN
+            )));
+        }
+        self.send(0x03, &message)
+    }
+
+    // moves and turns the player, the server will send them back if it does not allow it
+    pub fn move_to(&self, location: Location, on_ground: bool) -> Result<()> {
+        let packet = PlayerPositionAndRotation {
+            x: location.x,
+            y: location.y,
+            z: location.z,
+            yaw: location.yaw,
+            pitch: location.pitch,
+            on_ground,
+        };
+        self.send(0x13, &packet)?;
+        self.shared.lock().unwrap().location = Some(location);
+        Ok(())
+    }
+
+    // moves the player relative to where they are, if the server has sent that yet
+    pub fn move_by(&self, x: f64, y: f64, z: f64, on_ground: bool) -> Result<()> {
+        let location = match self.location() {
+            Some(location) => location,
+            None => Err(Error::Other(String::from("the player has not spawned yet")))?,
+        };
+
+        let location = Location {
+            x: location.x + x,
+            y: location.y + y,
+            z: location.z + z,
+            ..location
+        };
+        self.move_to(location, on_ground)
+    }
+
+    // turns the player without moving them
+    pub fn look(&self, yaw: f32, pitch: f32, on_ground: bool) -> Result<()> {
+        self.send(0x14, &(yaw, pitch, on_ground))?;
+        if let Some(location) = &mut self.shared.lock().unwrap().location {
+            location.yaw = yaw;
+            location.pitch = pitch;
+        }
+        Ok(())
+    }
+
+    // after dying
+    pub fn respawn(&self) -> Result<()> {
+        self.send(0x04, &VarInt(0))
+    }
+
+    // the reading thread stops, and sends Disconnected
+    pub fn disconnect(&self) {
+        let writer = self.writer.lock().unwrap();
+        let _ = writer.stream.shutdown(Shutdown::Both);
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        self.disconnect();
+    }
+}
+
+// the reading thread
+struct Play {
+    uuid: uuid::Uuid,
+    writer: Arc<Mutex<Writer>>,
+    shared: Arc<Mutex<Shared>>,
+    events: SyncSender<Event>,
+    // when the packet being handled came in, and by which read
+    read: (Instant, u64),
+    // the same for the last keep alive, until the round trip is done
+    keep_alive: Option<(Instant, u64)>,
+}
+
+impl Play {
+    fn run(&mut self, mut reader: Reader) {
+        let reason = loop {
+            let result = reader.next_frame().and_then(|(packet_id, body)| {
+                self.read = (reader.read_at, reader.reads);
+                self.round_trip();
+                self.packet(packet_id, body)
+            });
+
+            match result {
+                Ok(None) => {}
+                Ok(Some(reason)) => break reason,
+                Err(err) => break err.to_string(),
+            }
+        };
+
+        self.shared.lock().unwrap().connected = false;
+        let _ = reader.stream.shutdown(Shutdown::Both);
+        self.event(Event::Disconnected(reason));
+    }
+
+    fn send<T: Serialize>(&self, packet_id: i32, packet: &T) -> Result<()> {
+        self.writer.lock().unwrap().send(packet_id, packet)
+    }
+
+    // Some with the reason if the server disconnected
+    fn packet(&mut self, packet_id: i32, body: Vec<u8>) -> Result<Option<String>> {
+        self.shared
+            .lock()
+            .unwrap()
+            .stats
+            .add(State::Play, true, packet_id, body.len());
+
+        let event = match packet_id {
+            0x0E => {
+                let packet: ChatMessage = se::from_bytes(&body)?;
+                Event::Chat {
+                    message: Box::new(packet.message.0),
+                    position: packet.position,
+                    sender: packet.sender.0,
+                }
+            }
+            0x19 => {
+                let packet: Disconnect = se::from_bytes(&body)?;
+                return Ok(Some(packet.reason.0.to_plain()));
+            }
+            0x1F => {
+                let id: i64 = se::from_bytes(&body)?;
+                self.send(0x10, &id)?;
+                self.keep_alive = Some(self.read);
+                Event::KeepAlive(id)
+            }
+            0x24 => Event::JoinGame {
+                entity_id: se::from_bytes(&body)?,
+            },
+            0x32 => match self.latency(&body)? {
+                Some(latency) => Event::Latency(latency),
+                None => Event::Packet(packet_id, body),
+            },
+            0x34 => Event::Teleported(self.teleport(&body)?),
+            _ => Event::Packet(packet_id, body),
+        };
+
+        self.event(event);
+        Ok(None)
+    }
+
+    // the packets nobody asked for are dropped if the events are not being read, everything else
+    // waits for room. Sending only fails otherwise if the Client is gone, which has already closed
+    // the connection
+    fn event(&self, event: Event) {
+        if let Event::Packet(..) = event {
+            let _ = self.events.try_send(event);
+        } else {
+            let _ = self.events.send(event);
+        }
+    }
+
+    // packets from the same read as the keep alive were sent before the answer could have arrived
+    fn round_trip(&mut self) {
+        let (at, reads) = self.read;
+        match self.keep_alive {
+            Some((arrived, keep_alive_reads)) if reads > keep_alive_reads => {
+                self.keep_alive = None;
+                self.event(Event::KeepAliveRoundTrip(at - arrived));
+            }
+            _ => {}
+        }
+    }
+
+    fn teleport(&mut self, body: &[u8]) -> Result<Location> {
+        let packet: PlayerPositionAndLook = se::from_bytes(body)?;
+
+        // each bit makes a field relative to where the player was
+        let old = self.shared.lock().unwrap().location.unwrap_or_default();
+        let relative = |bit: u8, old: f64, new: f64| {
+            if packet.flags & bit != 0 {
+                old + new
+            } else {
+                new
+            }
+        };
+        let location = Location {
+            x: relative(0x01, old.x, packet.x),
+            y: relative(0x02, old.y, packet.y),
+            z: relative(0x04, old.z, packet.z),
+            yaw: relative(0x08, old.yaw.into(), packet.yaw.into()) as f32,
+            pitch: relative(0x10, old.pitch.into(), packet.pitch.into()) as f32,
+        };
+        self.shared.lock().unwrap().location = Some(location);
+
+        // like the vanilla client, confirm it and then say where the player is
+        self.send(0x00, &packet.teleport_id)?;
+        let packet = PlayerPositionAndRotation {
+            x: location.x,
+            y: location.y,
+            z: location.z,
+            yaw: location.yaw,
+            pitch: location.pitch,
+            on_ground: false,
+        };
+        self.send(0x13, &packet)?;
+
+        Ok(location)
+    }
+
+    // the latency of this player, if the Player Info packet has it
+    fn latency(&self, body: &[u8]) -> Result<Option<Duration>> {
+        const UPDATE_LATENCY: i32 = 2;
+
+        let action: VarInt = se::from_bytes(body)?;
+        if action.0 != UPDATE_LATENCY {
+            return Ok(None);
+        }
+
+        let (_, players): (VarInt, Vec<UpdateLatency>) = se::from_bytes(body)?;
+        let latency = players
+            .iter()
+            .find(|player| player.uuid.0 == self.uuid)
+            .map(|player| Duration::from_millis(player.ping.0.max(0) as u64));
+        Ok(latency)
+    }
+}
@@ -0,0 +1,253 @@
+// Splits a connection's byte stream into packets, including the compression and encryption that
+// get turned on during login
+//
+// A frame is a VarInt length followed by the packet id and body. After Set Compression, the length
+// is followed by a VarInt of the uncompressed length of the id and body, which is 0 if they were
+// under the threshold and are sent as is, and otherwise they are zlib compressed. After Encryption
+// Response, both directions are encrypted with AES-128 in CFB8 mode, using the shared secret as
+// both the key and the iv.
+
+use crate::se::{Error, Result, VarInt};
+use crate::MAX_PACKET_LEN;
+use aes::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use aes::Aes128;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::convert::TryFrom;
+use std::io::{Read, Write};
+
+pub const SECRET_LEN: usize = 16;
+
+type Decryptor = cfb8::Decryptor<Aes128>;
+type Encryptor = cfb8::Encryptor<Aes128>;
+
+// Ok(None) if data ends before the VarInt does, otherwise the value and its length
+fn peek_var_int(data: &[u8]) -> Result<Option<(i32, usize)>> {
+    let mut value: u32 = 0;
+
+    for (i, &byte) in data.iter().enumerate().take(5) {
+        value |= u32::from(byte & 0b01111111) << (7 * i);
+        if byte & 0b10000000 == 0 {
+            return Ok(Some((value as i32, i + 1)));
+        }
+    }
+
+    if data.len() >= 5 {
+        Err(Error::Packet(String::from("VarInt is longer than 5 bytes")))
+    } else {
+        Ok(None)
+    }
+}
+
+// splits a frame into the packet id and body
+pub fn split_packet_id(frame: &[u8]) -> Result<(i32, &[u8])> {
+    match peek_var_int(frame)? {
+        Some((packet_id, id_len)) => Ok((packet_id, &frame[id_len..])),
+        None => Err(Error::Packet(String::from("frame has no packet id"))),
+    }
+}
+
+fn parse_secret(secret: &[u8]) -> Result<[u8; SECRET_LEN]> {
+    let mut key = [0; SECRET_LEN];
+    if secret.len() != SECRET_LEN {
+        return Err(Error::Other(format!(
+            "shared secret is {} bytes long, it must be {}",
+            secret.len(),
+            SECRET_LEN
+        )));
+    }
+    key.copy_from_slice(secret);
+    Ok(key)
+}
+
+// a negative threshold turns compression off, like in the Set Compression packet
+fn parse_threshold(threshold: i32) -> Option<usize> {
+    if threshold < 0 {
+        None
+    } else {
+        Some(threshold as usize)
+    }
+}
+
+// Reads frames out of bytes as they arrive, in whatever pieces the network delivered them in
+#[derive(Default)]
+pub struct Decoder {
+    // decrypted bytes that have not been read as a frame yet
+    buffer: Vec<u8>,
+    cipher: Option<Decryptor>,
+    threshold: Option<usize>,
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.cipher.is_some()
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.threshold.is_some()
+    }
+
+    // everything after the frames read so far is encrypted,
+    // including the bytes that have already been pushed
+    pub fn enable_encryption(&mut self, secret: &[u8]) -> Result<()> {
+        let key = parse_secret(secret)?;
+        let mut cipher = Decryptor::new(&key.into(), &key.into());
+        for byte in self.buffer.chunks_mut(1) {
+            cipher.decrypt_block_mut(byte.into());
+        }
+        self.cipher = Some(cipher);
+        Ok(())
+    }
+
+    pub fn set_compression(&mut self, threshold: i32) {
+        self.threshold = parse_threshold(threshold);
+    }
+
+    pub fn push(&mut self, data: &[u8]) {
+        let start = self.buffer.len();
+        self.buffer.extend_from_slice(data);
+
+        if let Some(cipher) = &mut self.cipher {
+            for byte in self.buffer[start..].chunks_mut(1) {
+                cipher.decrypt_block_mut(byte.into());
+            }
+        }
+    }
+
+    // bytes pushed but not read as a frame yet
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    // the packet id and body of the next frame, decompressed,
+    // or None if it has not been pushed in full yet
+    pub fn next_frame(&mut self) -> Result<Option<Vec<u8>>> {
+        let (len, len_len) = match peek_var_int(&self.buffer)? {
+            Some(found) => found,
+            None => return Ok(None),
+        };
+        let len = usize::try_from(len)?;
+        if len > MAX_PACKET_LEN {
+            return Err(Error::Packet(format!(
+                "frame is {} bytes long, the maximum is {}",
+                len, MAX_PACKET_LEN
+            )));
+        }
+        if self.buffer.len() < len_len + len {
+            return Ok(None);
+        }
+
+        let frame: Vec<u8> = self.buffer.drain(..len_len + len).skip(len_len).collect();
+        match self.threshold {
+            Some(threshold) => decompress(&frame, threshold).map(Some),
+            None => Ok(Some(frame)),
+        }
+    }
+}
+
+fn decompress(frame: &[u8], threshold: usize) -> Result<Vec<u8>> {
+    let (data_len, data_len_len) = match peek_var_int(frame)? {
+        Some(found) => found,
+        None => {
+            return Err(Error::Packet(String::from(
+                "compressed frame has no data length",
+            )))
+        }
+    };
+    let body = &frame[data_len_len..];
+
+    // 0 means it was small enough to be sent uncompressed
+    if data_len == 0 {
+        return Ok(body.to_vec());
+    }
+
+    let data_len = usize::try_from(data_len)?;
+    if data_len < threshold || data_len > MAX_PACKET_LEN {
+        return Err(Error::Packet(format!(
+            "compressed packet claims to be {} bytes, the threshold is {}",
+            data_len, threshold
+        )));
+    }
+
+    let mut data = Vec::with_capacity(data_len);
+    ZlibDecoder::new(body)
+        .take(data_len as u64 + 1)
+        .read_to_end(&mut data)?;
+    if data.len() != data_len {
+        return Err(Error::Packet(format!(
+            "compressed packet is {} bytes, but claims to be {}",
+            data.len(),
+            data_len
+        )));
+    }
+
+    Ok(data)
+}
+
+// Turns packet ids and bodies into frames, the other side of a Decoder
+#[derive(Default)]
+pub struct Encoder {
+    cipher: Option<Encryptor>,
+    threshold: Option<usize>,
+}
+
+impl Encoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn enable_encryption(&mut self, secret: &[u8]) -> Result<()> {
+        let key = parse_secret(secret)?;
+        self.cipher = Some(Encryptor::new(&key.into(), &key.into()));
+        Ok(())
+    }
+
+    pub fn set_compression(&mut self, threshold: i32) {
+        self.threshold = parse_threshold(threshold);
+    }
+
+    // frame is the packet id followed by the body
+    pub fn encode(&mut self, frame: &[u8]) -> Result<Vec<u8>> {
+        let frame = match self.threshold {
+            Some(threshold) if frame.len() >= threshold => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(frame)?;
+                let compressed = encoder.finish()?;
+
+                let mut output = crate::se::serialize(VarInt::from_usize(frame.len())?)?;
+                output.extend(compressed);
+                output
+            }
+            Some(_) => {
+                let mut output = vec![0];
+                output.extend_from_slice(frame);
+                output
+            }
+            None => frame.to_vec(),
+        };
+
+        if frame.len() > MAX_PACKET_LEN {
+            return Err(Error::Packet(format!(
+                "frame is {} bytes long, the maximum is {}",
+                frame.len(),
+                MAX_PACKET_LEN
+            )));
+        }
+
+        let mut output = crate::se::serialize(VarInt::from_usize(frame.len())?)?;
+        output.extend(frame);
+
+        if let Some(cipher) = &mut self.cipher {
+            for byte in output.chunks_mut(1) {
+                cipher.encrypt_block_mut(byte.into());
+            }
+        }
+
+        Ok(output)
+    }
+}
@@ -0,0 +1,1616 @@
+// Decodes the packets of a recorded connection, for packet_inspector and packet_diff
+//
+// The input is split into Datas, runs of bytes sent by one side, which are read packet by packet.
+// Known packets are decoded into structs defined next to where they are read, and printed or
+// turned into json according to the Options.
+
+use crate::capture::CaptureReader;
+use crate::chat::{Json, StatusResponse, TextComponent};
+use crate::codec::Decoder;
+use crate::logic::State;
+use crate::se::{
+    from_bytes_debug, Angle, DeNBTBlob, Error, Hostname, Identifier, Input, Position, Result,
+    Username, Uuid, VarInt, Velocity,
+};
+use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::time::Duration;
+
+use serde_bytes::ByteBuf;
+
+mod customvec {
+    // Default Vec impl is a VarInt length followed by an array
+    // This is for Vecs with the prefixed length of a different type
+
+    use serde::de::Expected;
+    use std::fmt;
+    // why isnt serde::de::Expected implemented for more types
+    struct Index(usize);
+
+    impl Expected for Index {
+        fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+            fmt::Display::fmt(&self.0, fmt)
+        }
+    }
+
+    macro_rules! customvec_impl {
+        ($name:ident, $type:ty) => {
+            pub mod $name {
+                use super::Index;
+                use serde::de::{Deserialize, Deserializer, Error, SeqAccess, Unexpected, Visitor};
+                use serde::ser::{Serialize, Serializer};
+                use std::convert::TryFrom;
+                use std::marker::PhantomData;
+
+                pub fn deserialize<'de, D, T>(de: D) -> std::result::Result<Vec<T>, D::Error>
+                where
+                    D: Deserializer<'de>,
+                    T: Deserialize<'de>,
+                {
+                    struct CustomVecVisitor<T> {
+                        marker: PhantomData<T>,
+                    }
+
+                    impl<'de, T> Visitor<'de> for CustomVecVisitor<T>
+                    where
+                        T: Deserialize<'de>,
+                    {
+                        type Value = Vec<T>;
+
+                        fn expecting(
+                            &self,
+                            formatter: &mut std::fmt::Formatter,
+                        ) -> std::fmt::Result {
+                            formatter.write_str(concat!(
+                                "an array prefixed with its length as ",
+                                stringify!($type)
+                            ))
+                        }
+
+                        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+                        where
+                            A: SeqAccess<'de>,
+                        {
+                            let len: $type = match seq.next_element()? {
+                                Some(x) => x,
+                                None => {
+                                    return Err(Error::missing_field(concat!(
+                                        stringify!($type),
+                                        " len"
+                                    )))
+                                }
+                            };
+
+                            let len = match usize::try_from(len) {
+                                Ok(x) => x,
+                                Err(_) => {
+                                    return Err(Error::invalid_value(
+                                        Unexpected::Signed(len.into()),
+                                        &concat!("an ", stringify!($type), " > 0"),
+                                    ))
+                                }
+                            };
+
+                            let mut values = Vec::with_capacity(len);
+
+                            for i in 0..len {
+                                let val = match seq.next_element()? {
+                                    Some(x) => x,
+                                    None => {
+                                        return Err(Error::invalid_length(len, &Index(i)));
+                                    }
+                                };
+
+                                values.push(val);
+                            }
+
+                            Ok(values)
+                        }
+                    }
+
+                    let visitor = CustomVecVisitor {
+                        marker: PhantomData,
+                    };
+
+                    // the length is unknown and not actually 0,
+                    // but this is good enough since my Deserializer doesnt look at the length param
+                    // only works if (len, item0, item1, ...) == (len, (item0, item1, ...))
+                    de.deserialize_tuple(0, visitor)
+                }
+
+                pub fn serialize<T, S>(values: &[T], serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    T: Serialize,
+                    S: Serializer,
+                {
+                    use serde::ser::{Error, SerializeTuple};
+
+                    // formats like json have their own way of storing the length
+                    if serializer.is_human_readable() {
+                        return serializer.collect_seq(values);
+                    }
+
+                    let len = <$type>::try_from(values.len()).map_err(|_| {
+                        S::Error::custom(concat!(
+                            "too many items for an ",
+                            stringify!($type),
+                            " len"
+                        ))
+                    })?;
+
+                    // the same (len, item0, item1, ...) layout as deserialize
+                    let mut tuple = serializer.serialize_tuple(values.len() + 1)?;
+                    tuple.serialize_element(&len)?;
+                    for value in values {
+                        tuple.serialize_element(value)?;
+                    }
+                    tuple.end()
+                }
+            }
+        };
+    }
+
+    customvec_impl!(short, i16);
+    customvec_impl!(int, i32);
+}
+
+fn read_first<T>(slice: &[T]) -> &[T] {
+    &slice[..10.min(slice.len())]
+}
+
+pub struct Data {
+    pub is_server: bool,
+    pub data: Vec<u8>,
+    // since the unix epoch, if the input has them
+    pub timestamp: Option<Duration>,
+    // data is one frame (packet id and body) that was already decompressed and decrypted, instead
+    // of bytes as they were sent
+    pub is_frame: bool,
+}
+
+impl Data {
+    pub fn new(is_server: bool, data: Vec<u8>, timestamp: Option<Duration>) -> Self {
+        Data {
+            is_server,
+            data,
+            timestamp,
+            is_frame: false,
+        }
+    }
+
+    pub fn frame(is_server: bool, frame: Vec<u8>, timestamp: Option<Duration>) -> Self {
+        Data {
+            is_frame: true,
+            ..Data::new(is_server, frame, timestamp)
+        }
+    }
+}
+
+fn do_one_packet(frame: &[u8], data: &Data, inspector: &mut Inspector) -> Result<()> {
+    let (is_server, timestamp) = (data.is_server, data.timestamp);
+    let (packet_id, buffer) = crate::codec::split_packet_id(frame)?;
+
+    let mut handle = || -> Result<()> {
+        let state = inspector.state(is_server);
+        let index = inspector.read;
+        inspector.read += 1;
+        // frames were already decompressed and decrypted
+        if !data.is_frame {
+            inspector.update_framing(is_server, packet_id, buffer)?;
+        }
+        inspector.update_state(is_server, packet_id, buffer);
+        inspector
+            .stats
+            .add(state, is_server, packet_id, buffer.len());
+
+        let name = packet_name(state, is_server, packet_id);
+        if !inspector.options.matches(state, is_server, packet_id, name) {
+            return Ok(());
+        }
+        inspector.shown += 1;
+
+        if inspector.options.mode.is_json() {
+            inspector.record = Some(PacketRecord {
+                index,
+                timestamp: timestamp.map(|timestamp| timestamp.as_secs_f64()),
+                direction: if is_server { "server" } else { "client" },
+                state,
+                id: packet_id,
+                name,
+                length: buffer.len(),
+                fields: None,
+                error: None,
+            });
+        } else {
+            println!(
+                "#{} {} {:?} 0x{:02X} {} ({} bytes)",
+                index,
+                if is_server { "server" } else { "client" },
+                state,
+                packet_id,
+                name.unwrap_or("unknown"),
+                buffer.len()
+            );
+        }
+
+        match inspector.options.mode {
+            Mode::Summary => return Ok(()),
+            Mode::Hexdump => {
+                hexdump(buffer);
+                return Ok(());
+            }
+            Mode::Full | Mode::Json | Mode::Records => {}
+        }
+
+        match (state, is_server, packet_id) {
+            (State::Handshake, false, 0x00) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct Handshake<'a> {
+                    protocol_version: VarInt,
+                    #[serde(borrow)]
+                    address: Hostname<&'a str>,
+                    port: u16,
+                    next_state: VarInt,
+                }
+
+                let packet: Handshake = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Status, false, 0x00) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct Request {}
+
+                let packet: Request = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Status, true, 0x00) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct Response {
+                    json: Json<StatusResponse>,
+                }
+
+                let packet: Response = inspector.read_packet(buffer)?;
+                inspector.note(packet.json.0.description.to_ansi());
+                inspector.show(packet);
+            }
+            (State::Status, _is_pong, 0x01) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct PingPong(i64);
+
+                let packet: PingPong = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Login, false, 0x00) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct LoginStart<'a> {
+                    #[serde(borrow)]
+                    name: Username<&'a str>,
+                }
+
+                let packet: LoginStart = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Login, true, 0x00) | (State::Play, true, 0x19) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct Disconnect {
+                    reason: Json<TextComponent>,
+                }
+
+                let packet: Disconnect = inspector.read_packet(buffer)?;
+                inspector.note(packet.reason.0.to_ansi());
+                inspector.show(packet);
+            }
+            (State::Login, true, 0x01) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct EncryptionRequest {
+                    server_id: String,
+                    public_key: Vec<u8>,
+                    verify_token: Vec<u8>,
+                }
+
+                let packet: EncryptionRequest = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Login, false, 0x01) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct EncryptionResponse {
+                    shared_secret: Vec<u8>,
+                    verify_token: Vec<u8>,
+                }
+
+                let packet: EncryptionResponse = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Login, true, 0x02) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct LoginSuccess<'a> {
+                    uuid: Uuid,
+                    #[serde(borrow)]
+                    username: Username<&'a str>,
+                }
+
+                let packet: LoginSuccess = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Login, true, 0x03) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct SetCompression {
+                    threshold: VarInt,
+                }
+
+                let packet: SetCompression = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Play, false, 0x00) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct TeleportConfirm {
+                    teleport_id: VarInt,
+                }
+
+                let packet: TeleportConfirm = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Play, true, 0x02) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct SpawnLivingEntity {
+                    entity_id: VarInt,
+                    entity_uuid: Uuid,
+                    entity_type: VarInt,
+                    x: f64,
+                    y: f64,
+                    z: f64,
+                    yaw: Angle,
+                    pitch: Angle,
+                    head_pitch: Angle,
+                    velocity_x: Velocity,
+                    velocity_y: Velocity,
+                    velocity_z: Velocity,
+                }
+
+                let packet: SpawnLivingEntity = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Play, false, 0x03) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct ChatMessageClient {
+                    message: String,
+                }
+
+                let packet: ChatMessageClient = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Play, true, 0x0E) => {
+                #[derive(serde_repr::Deserialize_repr, serde_repr::Serialize_repr, Debug)]
+                #[repr(u8)]
+                enum ChatPosition {
+                    Chat = 0,
+                    System = 1,
+                    GameInfo = 2,
+                }
+
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct ChatMessageServer {
+                    message: Json<TextComponent>,
+                    position: ChatPosition,
+                    sender: Uuid,
+                }
+
+                let packet: ChatMessageServer = inspector.read_packet(buffer)?;
+                inspector.note(packet.message.0.to_ansi());
+                inspector.show(packet);
+            }
+            (State::Play, false, 0x05) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                enum ChatMode {
+                    Enabled,
+                    CommandsOnly,
+                    Hidden,
+                }
+
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                enum Hand {
+                    Left,
+                    Right,
+                }
+
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct ClientSettings {
+                    locale: String,
+                    view_distance: u8, // chunks
+                    chat_mode: ChatMode,
+                    chat_colors: bool,
+                    displayed_skin: u8, // bitmask on skin parts
+                    main_hand: Hand,
+                }
+
+                let packet: ClientSettings = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Play, false, 0x0B) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct PluginMessageClient {
+                    channel: Identifier,
+                    data: ByteBuf,
+                }
+
+                let packet: PluginMessageClient = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Play, true, 0x0D) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                enum Difficulty {
+                    Peaceful,
+                    Easy,
+                    Normal,
+                    Hard,
+                }
+
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct ServerDifficulty {
+                    difficulty: Difficulty,
+                    locked: bool,
+                }
+
+                let packet: ServerDifficulty = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Play, true, 0x10) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct DeclareCommands {
+                    node_len: VarInt,
+                    // nodes: Vec<Node>,
+                    // root_index: VarInt,
+                    rest: ByteBuf,
+                }
+
+                let packet: DeclareCommands = inspector.read_packet(buffer)?;
+                inspector.note("this packet impossible to parse: https://wiki.vg/Command_Data");
+                inspector.show(packet);
+            }
+            (State::Play, false, 0x12) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct PlayerPosition {
+                    pos: (f64, f64, f64),
+                    on_ground: bool,
+                }
+
+                let packet: PlayerPosition = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Play, false, 0x13) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct PlayerPosition {
+                    pos: (f64, f64, f64),
+                    yaw: f32,
+                    pitch: f32,
+                    on_ground: bool,
+                }
+
+                let packet: PlayerPosition = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Play, true, 0x13) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct Slot {
+                    item_id: VarInt,
+                    item_count: u8,
+                    nbt: (),
+                }
+
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct WindowItems {
+                    window_id: u8,
+                    #[serde(with = "customvec::short")]
+                    slots: Vec<Option<Slot>>,
+                }
+
+                let packet: WindowItems = inspector.read_packet(buffer)?;
+                inspector.blocked_on("nbt");
+                inspector.show(packet);
+            }
+            (State::Play, false, 0x15) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct PlayerMovement {
+                    on_ground: bool,
+                }
+
+                let packet: PlayerMovement = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Play, true, 0x15) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct Slot {
+                    item_id: VarInt,
+                    item_count: u8,
+                    nbt: (),
+                }
+
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct SetSlotInWindow {
+                    window_id: i8,
+                    slot: i16,
+                    data: Option<Slot>,
+                }
+
+                let packet: SetSlotInWindow = inspector.read_packet(buffer)?;
+                inspector.blocked_on("nbt");
+                inspector.show(packet);
+            }
+            (State::Play, true, 0x17) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct PluginMessageServer {
+                    channel: Identifier,
+                    data: ByteBuf,
+                }
+
+                let packet: PluginMessageServer = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Play, false, 0x1A) => {
+                #[derive(serde_repr::Deserialize_repr, serde_repr::Serialize_repr, Debug)]
+                #[repr(u8)]
+                enum PlayerAbilities {
+                    NotFlying = 0x00,
+                    Flying = 0x02,
+                }
+
+                let packet: PlayerAbilities = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Play, true, 0x1A) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct EntityStatus {
+                    id: i32,
+                    status: u8,
+                }
+
+                let packet: EntityStatus = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Play, true, 0x1F) | (State::Play, false, 0x10) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct KeepAlive(i64);
+
+                let packet: KeepAlive = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Play, true, 0x20) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct ChunkData {
+                    chunk_x: i32,
+                    chunk_y: i32,
+                    full_chunk: bool,
+                    primary_bit_mask: VarInt,
+                    rest: ByteBuf,
+                }
+
+                let packet: ChunkData = inspector.read_packet(buffer)?;
+                inspector.blocked_on("nbt");
+                inspector.show_min(packet);
+            }
+            (State::Play, true, 0x23) => {
+                #[derive(serde::Deserialize, serde::Serialize)]
+                struct LightArray(Vec<u8>);
+
+                impl Debug for LightArray {
+                    fn fmt(
+                        &self,
+                        fmt: &mut std::fmt::Formatter<'_>,
+                    ) -> std::result::Result<(), std::fmt::Error> {
+                        fmt.write_fmt(format_args!("[u8; {}]", self.0.len()))
+                    }
+                }
+
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct UpdateLight {
+                    chunk_x: VarInt,
+                    chunk_y: VarInt,
+                    trust_edges: bool,
+                    sky_light_mask: VarInt,
+                    block_light_mask: VarInt,
+                    empty_sky_light_mask: VarInt,
+                    empty_block_light_mask: VarInt,
+                    sky_light: LightArray,   // always 2048,
+                    block_light: LightArray, // always 2048,
+                }
+
+                let packet: UpdateLight = inspector.read_packet(buffer)?;
+                inspector.show_min(packet);
+            }
+            (State::Play, true, 0x24) => {
+                #[derive(serde_repr::Deserialize_repr, serde_repr::Serialize_repr, Debug)]
+                #[repr(i8)]
+                enum Gamemode {
+                    Survival = 0,
+                    Creative = 1,
+                    Adventure = 2,
+                    Spectator = 3,
+                }
+
+                #[derive(serde_repr::Deserialize_repr, serde_repr::Serialize_repr, Debug)]
+                #[repr(i8)]
+                enum PreviousGamemode {
+                    Survival = 0,
+                    Creative = 1,
+                    Adventure = 2,
+                    Spectator = 3,
+                    None = -1,
+                }
+
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct JoinGame {
+                    entity_id: i32,
+                    is_hardcore: bool,
+                    gamemode: Gamemode,
+                    prev_gamemode: PreviousGamemode,
+                    worlds: Vec<Identifier>,
+                    dimension_codec: DeNBTBlob,
+                    dimension: DeNBTBlob,
+                    spawn_world: Identifier,
+                    hashed_seed: i64,
+                    max_players: VarInt,
+                    view_distance: VarInt,
+                    reduced_debug: bool,
+                    not_immediate_respawn: bool,
+                    is_debug: bool,
+                    is_flat: bool,
+                }
+
+                let packet: JoinGame = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Play, true, 0x30) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct PlayerAbilities {
+                    flags: u8, // bitfield
+                    fly_speed: f32,
+                    fov_modifier: f32,
+                }
+
+                let packet: PlayerAbilities = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Play, true, 0x32) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct Properties {
+                    name: String,
+                    value: String,
+                    signature: Option<String>,
+                }
+
+                #[derive(serde_repr::Deserialize_repr, serde_repr::Serialize_repr, Debug)]
+                #[repr(i8)]
+                enum Gamemode {
+                    Survival = 0,
+                    Creative = 1,
+                    Adventure = 2,
+                    Spectator = 3,
+                }
+
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct Add {
+                    uuid: Uuid,
+                    name: String,
+                    properties: Vec<Properties>,
+                    gamemode: Gamemode,
+                    ping: VarInt, // time, in ms
+                    display_name: Option<TextComponent>,
+                }
+
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct UpdateGamemode {
+                    uuid: Uuid,
+                    gamemode: VarInt,
+                }
+
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct UpdateLatency {
+                    uuid: Uuid,
+                    ping: VarInt,
+                }
+
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct UpdateDisplayName {
+                    uuid: Uuid,
+                    display_name: Option<TextComponent>,
+                }
+
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct RemovePlayer {
+                    uuid: Uuid,
+                }
+
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                enum PlayerInfo {
+                    Add(Vec<Add>),
+                    UpdateGamemode(Vec<UpdateGamemode>),
+                    UpdateLatency(Vec<UpdateLatency>),
+                    UpdateDisplayName(Vec<UpdateDisplayName>),
+                    RemovePlayer(Vec<RemovePlayer>),
+                }
+
+                let packet: PlayerInfo = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Play, true, 0x34) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct PlayerPositionAndLook {
+                    pos: (f64, f64, f64),
+                    yaw: f32,
+                    pitch: f32,
+                    flags: u8,
+                    teleport_id: VarInt,
+                }
+
+                let packet: PlayerPositionAndLook = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Play, true, 0x35) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct Common {
+                    crafting_book: bool,
+                    crafting_filter: bool,
+                    smelting_book: bool,
+                    smelting_filter: bool,
+                    blast_furnace_book: bool,
+                    blast_furnace_filter: bool,
+                    smoker_book: bool,
+                    smoker_filter: bool,
+                    recipe_ids: Vec<Identifier>,
+                }
+
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                enum Action {
+                    Init(Common, Vec<Identifier>),
+                    Add(Common),
+                    Remove(Common),
+                }
+
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct UnlockRecipes {
+                    action: Action,
+                }
+
+                let packet: UnlockRecipes = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Play, true, 0x3D) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                enum WorldBorder {
+                    Diameter(f64),
+                    LerpSize {
+                        old_diameter: f64,
+                        new_diameter: f64,
+                        // speed: VarLong,
+                    },
+                    Center {
+                        x: f64,
+                        z: f64,
+                    },
+                    Initialize {
+                        x: f64,
+                        z: f64,
+                        old_diameter: f64,
+                        new_diameter: f64,
+                        // speed: VarLong,
+                        // etc
+                    },
+                    WarningTime(VarInt),
+                    WarningBlocks(VarInt),
+                }
+
+                let packet: WorldBorder = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Play, true, 0x3F) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct HeldItemChange {
+                    slot: u8, // which slot player selected, 0-8
+                }
+
+                let packet: HeldItemChange = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Play, true, 0x40) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct UpdateViewPosition {
+                    chunk_x: VarInt,
+                    chunk_z: VarInt,
+                }
+
+                let packet: UpdateViewPosition = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Play, true, 0x42) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct SpawnPosition(Position);
+
+                let packet: SpawnPosition = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Play, true, 0x44) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct EntityMetadata {
+                    entity_id: VarInt,
+                    // impossible to parse
+                }
+
+                let packet: EntityMetadata = inspector.read_packet(buffer)?;
+                inspector.blocked_on("0xff terminator vec");
+                inspector.show(packet);
+            }
+            (State::Play, true, 0x48) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct SetXP {
+                    xp_bar: f32, // 0-1
+                    level: VarInt,
+                    total_xp: VarInt,
+                }
+
+                let packet: SetXP = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Play, true, 0x49) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct UpdateHealth {
+                    health: f32,
+                    food: VarInt,
+                    saturation: f32,
+                }
+
+                let packet: UpdateHealth = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Play, true, 0x4E) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct TimeUpdate {
+                    world_age: i64,
+                    time_of_day: i64,
+                }
+
+                let packet: TimeUpdate = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Play, true, 0x57) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                enum Frame {
+                    Task,
+                    Challenge,
+                    Goal,
+                }
+
+                #[allow(non_camel_case_types)]
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                enum Flags {
+                    None,
+                    Background { texture: String },
+                    Toast,
+                }
+
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct Display {
+                    title: String,
+                    desc: String,
+                    icon: Option<()>, // nbt
+                    frame: Frame,
+                    flags: Flags,
+                    coords: (f32, f32),
+                }
+
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct Advancement {
+                    name: String,
+                    parent: Option<String>,
+                    display: Option<Display>,
+                    criteria: Vec<String>,
+                    requirements: Vec<Vec<String>>,
+                }
+
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct Progress {
+                    name: String,
+                    criteria: Vec<(String, Option<i64>)>, // name, achieved?, date of achieving
+                }
+
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct Advancements {
+                    reset_clear: bool,
+                    advancements: Vec<Advancement>,
+                    removed: Vec<String>,
+                    progress: Vec<Progress>,
+                }
+
+                let packet: Advancements = inspector.read_packet(buffer)?;
+                inspector.blocked_on("nbt");
+                inspector.show(packet);
+            }
+            (State::Play, true, 0x58) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                enum Operation {
+                    AbsoluteAdd, // value += amount
+                    PercentAdd,  // value += amount * value
+                    Multiply,    // value *= amount
+                }
+
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct Modifier {
+                    uuid: Uuid,
+                    amount: f64,
+                    operation: Operation,
+                }
+
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct Property {
+                    key: String,
+                    value: f64,
+                    modifiers: Vec<Modifier>,
+                }
+
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct EntityProperties {
+                    entity_id: VarInt,
+                    #[serde(with = "customvec::int")]
+                    properties: Vec<Property>,
+                }
+
+                let packet: EntityProperties = inspector.read_packet(buffer)?;
+                inspector.show(packet);
+            }
+            (State::Play, true, 0x5A) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct Recipes {
+                    len: VarInt,
+                    rest: ByteBuf,
+                }
+
+                let packet: Recipes = inspector.read_packet(buffer)?;
+                inspector.blocked_on("string enum");
+                inspector.note(format_args!("number of recipes read: {}", packet.len));
+                inspector.fields(&packet);
+                // inspector.show_min(packet);
+            }
+            (State::Play, true, 0x5B) => {
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct Tag {
+                    name: Identifier,
+                    entries: Vec<VarInt>,
+                }
+
+                #[derive(serde::Deserialize, serde::Serialize, Debug)]
+                struct Tags {
+                    blocks: Vec<Tag>,
+                    items: Vec<Tag>,
+                    fluids: Vec<Tag>,
+                    entities: Vec<Tag>,
+                }
+
+                let packet: Tags = inspector.read_packet(buffer)?;
+                inspector.note("packet fully parsed; display suppressed due to large size");
+                inspector.note(format_args!(
+                    "Tags {{ blocks: {}, items: {}, fluids: {}, entities: {} }}",
+                    packet.blocks.len(),
+                    packet.items.len(),
+                    packet.fluids.len(),
+                    packet.entities.len()
+                ));
+                inspector.fields(&packet);
+                // inspector.show_min(packet);
+            }
+            _ => {
+                inspector.note("unknown packet");
+            }
+        }
+        Ok(())
+    };
+    let result = handle();
+
+    // in json mode, a packet that fails to decode is reported and skipped over
+    match inspector.record.take() {
+        Some(mut record) => {
+            if let Err(err) = result {
+                record.error = Some(err.to_string());
+            }
+            if inspector.options.mode == Mode::Records {
+                inspector.records.push(record);
+            } else {
+                let line = serde_json::to_string(&record)
+                    .map_err(|err| crate::se::Error::Other(err.to_string()))?;
+                println!("{}", line);
+            }
+            Ok(())
+        }
+        None => result,
+    }
+}
+
+// 16 bytes per line, with the offset and the printable characters
+fn hexdump(data: &[u8]) {
+    for (i, line) in data.chunks(16).enumerate() {
+        let hex: Vec<String> = line.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let text: String = line
+            .iter()
+            .map(|&byte| {
+                if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        println!("    {:08x}  {:<47}  {}", i * 16, hex.join(" "), text);
+    }
+}
+
+// names for the packets of protocol 754 (1.16.4) that show up in our captures
+pub fn packet_name(state: State, is_server: bool, packet_id: i32) -> Option<&'static str> {
+    let name = match (state, is_server, packet_id) {
+        (State::Handshake, false, 0x00) => "Handshake",
+
+        (State::Status, false, 0x00) => "Request",
+        (State::Status, true, 0x00) => "Response",
+        (State::Status, false, 0x01) => "Ping",
+        (State::Status, true, 0x01) => "Pong",
+
+        (State::Login, false, 0x00) => "LoginStart",
+        (State::Login, false, 0x01) => "EncryptionResponse",
+        (State::Login, true, 0x00) => "Disconnect",
+        (State::Login, true, 0x01) => "EncryptionRequest",
+        (State::Login, true, 0x02) => "LoginSuccess",
+        (State::Login, true, 0x03) => "SetCompression",
+
+        (State::Play, false, 0x00) => "TeleportConfirm",
+        (State::Play, false, 0x03) => "ChatMessage",
+        (State::Play, false, 0x04) => "ClientStatus",
+        (State::Play, false, 0x05) => "ClientSettings",
+        (State::Play, false, 0x0B) => "PluginMessage",
+        (State::Play, false, 0x10) => "KeepAlive",
+        (State::Play, false, 0x12) => "PlayerPosition",
+        (State::Play, false, 0x13) => "PlayerPositionAndRotation",
+        (State::Play, false, 0x14) => "PlayerRotation",
+        (State::Play, false, 0x15) => "PlayerMovement",
+        (State::Play, false, 0x1A) => "PlayerAbilities",
+
+        (State::Play, true, 0x02) => "SpawnLivingEntity",
+        (State::Play, true, 0x0D) => "ServerDifficulty",
+        (State::Play, true, 0x0E) => "ChatMessage",
+        (State::Play, true, 0x10) => "DeclareCommands",
+        (State::Play, true, 0x13) => "WindowItems",
+        (State::Play, true, 0x15) => "SetSlot",
+        (State::Play, true, 0x17) => "PluginMessage",
+        (State::Play, true, 0x19) => "Disconnect",
+        (State::Play, true, 0x1A) => "EntityStatus",
+        (State::Play, true, 0x1F) => "KeepAlive",
+        (State::Play, true, 0x20) => "ChunkData",
+        (State::Play, true, 0x23) => "UpdateLight",
+        (State::Play, true, 0x24) => "JoinGame",
+        (State::Play, true, 0x30) => "PlayerAbilities",
+        (State::Play, true, 0x32) => "PlayerInfo",
+        (State::Play, true, 0x34) => "PlayerPositionAndLook",
+        (State::Play, true, 0x35) => "UnlockRecipes",
+        (State::Play, true, 0x3D) => "WorldBorder",
+        (State::Play, true, 0x3F) => "HeldItemChange",
+        (State::Play, true, 0x40) => "UpdateViewPosition",
+        (State::Play, true, 0x42) => "SpawnPosition",
+        (State::Play, true, 0x44) => "EntityMetadata",
+        (State::Play, true, 0x48) => "SetExperience",
+        (State::Play, true, 0x49) => "UpdateHealth",
+        (State::Play, true, 0x4E) => "TimeUpdate",
+        (State::Play, true, 0x57) => "Advancements",
+        (State::Play, true, 0x58) => "EntityProperties",
+        (State::Play, true, 0x5A) => "DeclareRecipes",
+        (State::Play, true, 0x5B) => "Tags",
+
+        _ => return None,
+    };
+
+    Some(name)
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Mode {
+    // one line per packet
+    Summary,
+    // the line, followed by the decoded packet
+    #[default]
+    Full,
+    // the line, followed by the raw packet body
+    Hexdump,
+    // one json object per line for each packet, everything else goes to stderr
+    Json,
+    // the json objects are kept in Inspector::records, and nothing is printed
+    Records,
+}
+
+impl Mode {
+    fn is_json(self) -> bool {
+        matches!(self, Self::Json | Self::Records)
+    }
+}
+
+// which packets to show and how, from the command line
+#[derive(Clone, Default)]
+pub struct Options {
+    pub mode: Mode,
+    pub is_server: Option<bool>,
+    pub state: Option<State>,
+    pub ids: Vec<i32>,
+    // compared ignoring case
+    pub names: Vec<String>,
+    pub max_packets: Option<usize>,
+    // the shared secret of an encrypted connection, to decrypt it with
+    pub secret: Option<Vec<u8>>,
+}
+
+impl Options {
+    fn matches(&self, state: State, is_server: bool, packet_id: i32, name: Option<&str>) -> bool {
+        let name_matches = |name: Option<&str>| match name {
+            Some(name) => self
+                .names
+                .iter()
+                .any(|filter| filter.eq_ignore_ascii_case(name)),
+            None => false,
+        };
+
+        self.is_server.unwrap_or(is_server) == is_server
+            && self.state.unwrap_or(state) == state
+            && (self.ids.is_empty() || self.ids.contains(&packet_id))
+            && (self.names.is_empty() || name_matches(name))
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct PacketStats {
+    pub count: usize,
+    pub bytes: usize,
+}
+
+// counts every packet read, whether or not it was shown
+#[derive(Clone, Default)]
+pub struct Stats {
+    pub packets: HashMap<(State, bool, i32), PacketStats>,
+}
+
+impl Stats {
+    pub fn add(&mut self, state: State, is_server: bool, packet_id: i32, len: usize) {
+        let stats = self
+            .packets
+            .entry((state, is_server, packet_id))
+            .or_default();
+        stats.count += 1;
+        stats.bytes += len;
+    }
+
+    // adds up the packets of another connection
+    pub fn merge(&mut self, other: &Stats) {
+        for (&key, other) in &other.packets {
+            let stats = self.packets.entry(key).or_default();
+            stats.count += other.count;
+            stats.bytes += other.bytes;
+        }
+    }
+
+    pub fn print(&self) {
+        let mut keys: Vec<_> = self.packets.keys().collect();
+        keys.sort_by_key(|&&(state, is_server, packet_id)| (state as u8, is_server, packet_id));
+
+        for key in keys {
+            let (state, is_server, packet_id) = *key;
+            let stats = &self.packets[key];
+            println!(
+                "{} {:?} 0x{:02X} {}: {} packets, {} bytes",
+                if is_server { "server" } else { "client" },
+                state,
+                packet_id,
+                packet_name(state, is_server, packet_id).unwrap_or("unknown"),
+                stats.count,
+                stats.bytes
+            );
+        }
+    }
+}
+
+// a line of json mode output
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct PacketRecord {
+    // counts every packet read, whether or not it was shown
+    pub index: usize,
+    // seconds since the unix epoch
+    pub timestamp: Option<f64>,
+    pub direction: &'static str,
+    pub state: State,
+    pub id: i32,
+    pub name: Option<&'static str>,
+    pub length: usize,
+    // the decoded packet, if it is a known one
+    pub fields: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub struct Inspector {
+    pub options: Options,
+    // the connection state the next packet from each side is read in
+    client_state: State,
+    server_state: State,
+    // splits the bytes sent by each side into frames
+    client_decoder: Decoder,
+    server_decoder: Decoder,
+    // the connection was encrypted and there is no secret to decrypt it with
+    undecryptable: bool,
+    pub stats: Stats,
+    // number of packets read so far
+    read: usize,
+    // number of packets that passed the filters
+    shown: usize,
+    // in json mode, the packet being decoded
+    record: Option<PacketRecord>,
+    // in records mode, every packet that was shown
+    pub records: Vec<PacketRecord>,
+}
+
+impl Inspector {
+    pub fn new(options: Options) -> Self {
+        Self {
+            options,
+            client_state: State::default(),
+            server_state: State::default(),
+            client_decoder: Decoder::new(),
+            server_decoder: Decoder::new(),
+            undecryptable: false,
+            stats: Stats::default(),
+            read: 0,
+            shown: 0,
+            record: None,
+            records: Vec::new(),
+        }
+    }
+
+    // anything that is not the packet itself
+    fn note<T: Display>(&self, message: T) {
+        match self.options.mode {
+            Mode::Json => eprintln!("{}", message),
+            Mode::Records => {}
+            _ => println!("{}", message),
+        }
+    }
+
+    fn blocked_on(&self, feature: &'static str) {
+        self.note(format_args!("parsing is blocked on {} feature", feature));
+    }
+
+    fn read_packet<'a, T>(&self, buffer: Input<'a>) -> Result<T>
+    where
+        T: serde::Deserialize<'a> + serde::Serialize,
+    {
+        self.note(format_args!(
+            "Packet of type {}:",
+            std::any::type_name::<T>()
+        ));
+        let (rest_input, packet) = from_bytes_debug(buffer);
+        if let Some(diagnostic) = packet.as_ref().err().and_then(|err| err.diagnostic()) {
+            self.note(format_args!("{:?}", diagnostic));
+        }
+        let packet = packet?;
+        if !rest_input.is_empty() {
+            self.note(format_args!(
+                "warning: unread length {}: {:?}",
+                rest_input.len(),
+                read_first(rest_input)
+            ));
+        }
+
+        // let seri = crate::se::serialize(&packet).unwrap();
+        // if buffer != seri {
+        //     panic!("unequal type {}:", std::any::type_name::<T>());
+        // }
+
+        Ok(packet)
+    }
+
+    // the decoded packet, for json mode
+    fn fields<T: serde::Serialize>(&mut self, packet: &T) {
+        if let Some(record) = &mut self.record {
+            match serde_json::to_value(packet) {
+                Ok(fields) => record.fields = Some(fields),
+                Err(err) => record.error = Some(err.to_string()),
+            }
+        }
+    }
+
+    fn show<T: Debug + serde::Serialize>(&mut self, packet: T) {
+        match self.options.mode {
+            Mode::Json | Mode::Records => self.fields(&packet),
+            _ => println!("{:#?}", packet),
+        }
+    }
+
+    fn show_min<T: Debug + serde::Serialize>(&mut self, packet: T) {
+        match self.options.mode {
+            Mode::Json | Mode::Records => self.fields(&packet),
+            _ => println!("{:?}", packet),
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.undecryptable
+            || matches!(self.options.max_packets, Some(max_packets) if self.shown >= max_packets)
+    }
+
+    fn decoder(&mut self, is_server: bool) -> &mut Decoder {
+        if is_server {
+            &mut self.server_decoder
+        } else {
+            &mut self.client_decoder
+        }
+    }
+
+    // like the state, compression and encryption start for both sides after the packet
+    fn update_framing(&mut self, is_server: bool, packet_id: i32, buffer: Input) -> Result<()> {
+        match (self.state(is_server), is_server, packet_id) {
+            (State::Login, true, 0x03) => {
+                let threshold: VarInt = crate::se::from_bytes(buffer)?;
+                self.client_decoder.set_compression(threshold.0);
+                self.server_decoder.set_compression(threshold.0);
+            }
+            (State::Login, false, 0x01) => match &self.options.secret {
+                Some(secret) => {
+                    self.client_decoder.enable_encryption(secret)?;
+                    self.server_decoder.enable_encryption(secret)?;
+                }
+                None => {
+                    self.note("the rest of the connection is encrypted, a shared secret is needed to read it");
+                    self.undecryptable = true;
+                }
+            },
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn state(&self, is_server: bool) -> State {
+        if is_server {
+            self.server_state
+        } else {
+            self.client_state
+        }
+    }
+
+    // each side's packets are read in the state that side is in, see State::after_packet. A
+    // disconnect only closes the sender's side, whatever the other side sent before reading it is
+    // still decoded
+    fn update_state(&mut self, is_server: bool, packet_id: i32, buffer: Input) {
+        let next = match self
+            .state(is_server)
+            .after_packet(is_server, packet_id, buffer)
+        {
+            Some(next) => next,
+            None => return,
+        };
+
+        let (sender, receiver) = if is_server {
+            (&mut self.server_state, &mut self.client_state)
+        } else {
+            (&mut self.client_state, &mut self.server_state)
+        };
+        *sender = next;
+        if next != State::Closed {
+            *receiver = next;
+        }
+    }
+}
+
+fn do_one_frame(frame: &[u8], data: &Data, inspector: &mut Inspector) -> Result<()> {
+    let shown = inspector.shown;
+    do_one_packet(frame, data, inspector)?;
+    if inspector.options.mode == Mode::Full && inspector.shown > shown {
+        println!();
+    }
+
+    Ok(())
+}
+
+pub fn do_one_data(data: &Data, inspector: &mut Inspector) -> Result<()> {
+    if data.is_frame {
+        if !inspector.is_done() {
+            do_one_frame(&data.data, data, inspector)?;
+        }
+        return Ok(());
+    }
+
+    // a packet can be split across datas, so whatever is left over waits for the next one
+    inspector.decoder(data.is_server).push(&data.data);
+    while !inspector.is_done() {
+        match inspector.decoder(data.is_server).next_frame()? {
+            Some(frame) => do_one_frame(&frame, data, inspector)?,
+            None => break,
+        }
+    }
+
+    Ok(())
+}
+
+pub fn do_all_data(datas: &[Data], inspector: &mut Inspector) -> Result<()> {
+    for data in datas {
+        do_one_data(data, inspector)?;
+    }
+
+    for &is_server in &[false, true] {
+        let left_over = inspector.decoder(is_server).buffered();
+        if left_over > 0 && !inspector.is_done() {
+            inspector.note(format_args!(
+                "warning: the last {} bytes sent by the {} are not a whole packet",
+                left_over,
+                if is_server { "server" } else { "client" }
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// every packet, decoded into json, for comparing captures
+pub fn read_records(datas: &[Data]) -> Result<Vec<PacketRecord>> {
+    let mut inspector = Inspector::new(Options {
+        mode: Mode::Records,
+        ..Options::default()
+    });
+    do_all_data(datas, &mut inspector)?;
+
+    Ok(inspector.records)
+}
+
+fn remove_whitespace(s: &str) -> String {
+    s.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    // a wireshark tcp stream dump
+    Yaml,
+    // recorded by digmake, see capture.rs
+    Capture,
+    // tcpdump or wireshark, see pcap.rs
+    Pcap,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "yaml" => Some(Self::Yaml),
+            "capture" => Some(Self::Capture),
+            "pcap" => Some(Self::Pcap),
+            _ => None,
+        }
+    }
+
+    // anything that is not yaml or pcap is assumed to be a capture
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some("yaml") => Self::Yaml,
+            Some("pcap") | Some("pcapng") => Self::Pcap,
+            _ => Self::Capture,
+        }
+    }
+}
+
+// port is the server port for pcap input, which is guessed if not given
+pub fn read_data<P: AsRef<Path>>(path: P, format: Format, port: Option<u16>) -> Result<Vec<Data>> {
+    match format {
+        Format::Yaml => read_data_from_file(path),
+        Format::Capture => read_data_from_capture(path),
+        Format::Pcap => read_data_from_pcap(path, port),
+    }
+}
+
+// read from a wireshark tcp stream dump in yaml format
+pub fn read_data_from_file<P: AsRef<Path>>(filename: P) -> Result<Vec<Data>> {
+    let yaml_error = |msg: String| Error::Other(format!("yaml stream dump: {}", msg));
+
+    let data: serde_yaml::Value = {
+        let file = File::open(filename)?;
+        let data = serde_yaml::from_reader(&file);
+        file.sync_all()?;
+        data.map_err(|err| yaml_error(err.to_string()))?
+    };
+
+    let data = match data.as_mapping() {
+        Some(data) => data,
+        None => return Err(yaml_error(String::from("not a mapping"))),
+    };
+    let mut output = Vec::with_capacity(data.len()); // data.len() == tcp packets, upper limit on number of Datas
+    let mut data = data.into_iter();
+
+    let mut read_one_physical_packet = || -> Result<Option<Data>> {
+        let (key, value) = match data.next() {
+            Some(val) => val,
+            None => return Ok(None),
+        };
+        let (key, value) = match (key.as_str(), value.as_str()) {
+            (Some(key), Some(value)) => (key, value),
+            _ => return Err(yaml_error(format!("{:?} is not a string", key))),
+        };
+
+        let is_server = {
+            // wow i hope this is consistent
+            if key.starts_with("peer0") {
+                false
+            } else if key.starts_with("peer1") {
+                true
+            } else {
+                return Err(yaml_error(format!("{} is not from peer0 or peer1", key)));
+            }
+        };
+        let value = remove_whitespace(value);
+        // println!(
+        //     "read physical packet: {} {:?}...",
+        //     is_server,
+        //     value.chars().take(50).collect::<String>()
+        // );
+        let packet_data =
+            base64::decode(&value).map_err(|err| yaml_error(format!("{}: {}", key, err)))?;
+
+        Ok(Some(Data::new(is_server, packet_data, None)))
+    };
+
+    // combine all consecutive packets from one peer into a single packet
+    // because logical mc packets can span many physical tcp packets.
+    // and although each Data struct can have many mc packets,
+    // a packet cannot be split into multiple Data structs.
+
+    if let Some(mut data) = read_one_physical_packet()? {
+        loop {
+            let mut next_data = match read_one_physical_packet()? {
+                Some(d) => d,
+                None => {
+                    output.push(data);
+                    break;
+                }
+            };
+
+            if data.is_server == next_data.is_server {
+                data.data.append(&mut next_data.data);
+            } else {
+                output.push(data);
+                data = next_data;
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+// read from a capture recorded by digmake
+pub fn read_data_from_capture<P: AsRef<Path>>(filename: P) -> Result<Vec<Data>> {
+    let file = BufReader::new(File::open(filename)?);
+    let mut reader = CaptureReader::new(file)?;
+    let started = Duration::from_millis(reader.header().started);
+    let mut output: Vec<Data> = Vec::new();
+
+    // each record is a whole packet, so it gets its own Data and keeps its timestamp
+    for record in reader.by_ref() {
+        let record = record?;
+        let timestamp = started + record.timestamp();
+        output.push(Data::frame(
+            record.direction.is_server(),
+            record.frame,
+            Some(timestamp),
+        ));
+    }
+
+    Ok(output)
+}
+
+// read the first minecraft connection out of a packet capture (tcpdump, wireshark)
+// the server port is guessed if not given
+pub fn read_data_from_pcap<P: AsRef<Path>>(filename: P, port: Option<u16>) -> Result<Vec<Data>> {
+    let file = std::fs::read(filename)?;
+
+    Ok(crate::pcap::read_connection(&file, port)?
+        .into_iter()
+        .map(|chunk| Data::new(chunk.is_server, chunk.data, Some(chunk.timestamp)))
+        .collect())
+}
@@ -1,11 +1,23 @@
+pub mod capture;
+pub mod chat;
+pub mod client;
+pub mod codec;
+pub mod inspector;
+pub mod loadtest;
 pub mod logic;
+pub mod pcap;
+pub mod proxy;
+pub mod replay;
 pub mod se;
 mod util;
 
 use crate::se::VarInt;
 use se::{Error, Result};
-use std::io::{BufRead, BufReader};
-use std::net::TcpStream;
+use serde::Serialize;
+use std::io::{self, BufRead, Read, Write};
+
+// largest length that fits in the 3 byte VarInt the vanilla client and server allow
+pub const MAX_PACKET_LEN: usize = (1 << 21) - 1;
 
 // todo: custom enum for packet problems
 
@@ -25,10 +37,6 @@ where
     // util::count_reads counts the number of bytes read from the reader by the closure
     let (packet_id, id_len) = util::count_reads(&mut reader, |reader| VarInt::_parse(reader));
     let packet_id = packet_id?;
-    println!(
-        "packet_len: {}, packet_id: 0x{:02X}, id_len: {}",
-        packet_len, packet_id, id_len
-    );
 
     let packet_len = packet_len.checked_sub(id_len).ok_or_else(|| {
         Error::Packet(format!(
@@ -69,3 +77,45 @@ where
 
     Ok(packet_id)
 }
+
+// like read_packeta, but the handler reads the packet body straight out of the reader
+// (eg. with se::from_reader), so packets that dont fit in the buffer are never copied.
+// anything the handler leaves unread is skipped, so the next packet starts in the right place
+pub fn read_packet_streaming<T, F, U>(mut reader: &mut T, handler: F) -> Result<U>
+where
+    T: BufRead,
+    F: FnOnce(i32, &mut io::Take<&mut T>) -> Result<U>,
+{
+    let (packet_id, packet_len) = read_packet_id_len(&mut reader)?;
+
+    let mut body = reader.take(packet_len as u64);
+    let result = handler(packet_id, &mut body);
+    io::copy(&mut body, &mut io::sink())?;
+
+    result
+}
+
+// writes the packet length, id, and data without serializing the packet into a temporary buffer
+pub fn write_packet<W, T>(writer: W, packet_id: i32, data: &T) -> Result<()>
+where
+    W: Write,
+    T: Serialize,
+{
+    let packet_id = VarInt(packet_id);
+    let packet_len = packet_id.encoded_len() + se::encoded_len(data)?;
+
+    if packet_len > MAX_PACKET_LEN {
+        return Err(Error::Packet(format!(
+            "packet 0x{:02X} is {} bytes long, the maximum is {}",
+            packet_id.0, packet_len, MAX_PACKET_LEN
+        )));
+    }
+
+    // small packets go out in one write, instead of one for the length, id and data each
+    let mut writer = io::BufWriter::new(writer);
+    se::serialize_to_writer(&mut writer, VarInt::from_usize(packet_len)?)?;
+    se::serialize_to_writer(&mut writer, packet_id)?;
+    se::serialize_to_writer(&mut writer, data)?;
+    writer.flush()?;
+    Ok(())
+}
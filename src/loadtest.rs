@@ -0,0 +1,270 @@
+// Connects a crowd of bots to a server, to see how it copes
+//
+// Each bot is a Client on its own thread. Bots are started one after another, log in, and then
+// walk back and forth and chat at the rates given until the test is over, when they all leave.
+// What happened to every bot is put together into a Report: how long logging in took, the keep
+// alive round trips, the ping the server reported, how evenly the keep alives came in, the packets
+// sent and received, and why bots were disconnected.
+//
+// Each keep alive round trip is timed by the bot, from the keep alive coming in to the first
+// packet the server sent after the answer, see Event::KeepAliveRoundTrip. The server-reported ping
+// is the one in the tab list, which the vanilla server keeps as a smoothed average of the round
+// trips it times and sends about every 30 seconds, so shorter runs may not get any. The server
+// sends keep alives at a steady interval, so the jitter is how much one gap between them differs
+// from the one before, which grows when the server or the network falls behind.
+
+use crate::client::{Client, Event};
+use crate::inspector::Stats;
+use std::collections::HashMap;
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::{Duration, Instant};
+
+// how far a bot walks each time it moves, a little under walking speed at 20 moves a second
+const MOVE_STEP: f64 = 0.2;
+// how many moves before a bot turns around
+const MOVES_PER_LEG: u32 = 20;
+
+#[derive(Clone, Debug)]
+pub struct LoadTestOptions {
+    pub address: String,
+    pub bots: usize,
+    // bots are named this followed by their number
+    pub name_prefix: String,
+    // time between starting bots
+    pub connect_interval: Duration,
+    // how long each bot stays once it has logged in
+    pub duration: Duration,
+    // per bot, per second, 0 to not do it at all
+    pub move_rate: f64,
+    pub chat_rate: f64,
+}
+
+// what happened to one bot
+#[derive(Default)]
+struct BotResult {
+    // None if it could not log in
+    connect_time: Option<Duration>,
+    round_trips: Vec<Duration>,
+    pings: Vec<Duration>,
+    // when each keep alive came in
+    keep_alives: Vec<Instant>,
+    stats: Stats,
+    // None if it was still connected at the end
+    disconnect_reason: Option<String>,
+}
+
+fn rate_interval(rate: f64) -> Option<Duration> {
+    if rate > 0.0 {
+        Some(Duration::from_secs_f64(1.0 / rate))
+    } else {
+        None
+    }
+}
+
+fn run_bot(options: &LoadTestOptions, number: usize) -> BotResult {
+    let mut result = BotResult::default();
+
+    let start = Instant::now();
+    let client = match Client::connect(
+        options.address.as_str(),
+        &format!("{}{}", options.name_prefix, number),
+    ) {
+        Ok(client) => client,
+        Err(err) => {
+            result.disconnect_reason = Some(format!("could not log in: {}", err));
+            return result;
+        }
+    };
+    result.connect_time = Some(start.elapsed());
+
+    let end = Instant::now() + options.duration;
+    let move_interval = rate_interval(options.move_rate);
+    let chat_interval = rate_interval(options.chat_rate);
+    let mut next_move = move_interval.map(|interval| Instant::now() + interval);
+    let mut next_chat = chat_interval.map(|interval| Instant::now() + interval);
+    let (mut moves, mut chats) = (0u32, 0u32);
+
+    loop {
+        let now = Instant::now();
+        if now >= end {
+            break;
+        }
+
+        if let (Some(at), Some(interval)) = (next_move, move_interval) {
+            if now >= at {
+                // walks along x, and turns around every so often
+                let is_back = (moves / MOVES_PER_LEG) % 2 == 1;
+                let step = if is_back { -MOVE_STEP } else { MOVE_STEP };
+                // nothing to move from until the server has said where the bot spawned
+                if client.location().is_some() {
+                    if let Err(err) = client.move_by(step, 0.0, 0.0, true) {
+                        result.disconnect_reason = Some(err.to_string());
+                        break;
+                    }
+                    moves += 1;
+                }
+                next_move = Some(at + interval);
+            }
+        }
+
+        if let (Some(at), Some(interval)) = (next_chat, chat_interval) {
+            if now >= at {
+                let message = format!("bot {} says hello {}", number, chats);
+                if let Err(err) = client.chat(&message) {
+                    result.disconnect_reason = Some(err.to_string());
+                    break;
+                }
+                chats += 1;
+                next_chat = Some(at + interval);
+            }
+        }
+
+        let wake = [Some(end), next_move, next_chat]
+            .iter()
+            .flatten()
+            .min()
+            .copied()
+            .unwrap_or(end);
+        let timeout = wake.saturating_duration_since(Instant::now());
+        match client.events().recv_timeout(timeout) {
+            Ok(Event::KeepAliveRoundTrip(round_trip)) => result.round_trips.push(round_trip),
+            Ok(Event::Latency(ping)) => result.pings.push(ping),
+            Ok(Event::KeepAlive(_)) => result.keep_alives.push(Instant::now()),
+            Ok(Event::Disconnected(reason)) => {
+                result.disconnect_reason = Some(reason);
+                break;
+            }
+            Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    client.disconnect();
+    result.stats = client.stats();
+    result
+}
+
+pub struct Report {
+    pub bots: usize,
+    // of the bots that logged in
+    pub connect_times: Vec<Duration>,
+    pub round_trips: Vec<Duration>,
+    pub pings: Vec<Duration>,
+    // how much each gap between a bot's keep alives differs from the one before
+    pub keep_alive_jitter: Vec<Duration>,
+    // everything sent and received by all of the bots
+    pub stats: Stats,
+    // how many bots were disconnected or could not log in, for each reason
+    pub disconnect_reasons: HashMap<String, usize>,
+}
+
+// the value that p percent of the values are at or below, values must be sorted
+pub fn percentile(values: &[Duration], p: f64) -> Option<Duration> {
+    if values.is_empty() {
+        return None;
+    }
+    let rank = (p / 100.0 * values.len() as f64).ceil() as usize;
+    Some(values[rank.clamp(1, values.len()) - 1])
+}
+
+fn print_durations(name: &str, values: &[Duration]) {
+    let ms = |p| match percentile(values, p) {
+        Some(value) => format!("{:.1}ms", value.as_secs_f64() * 1000.0),
+        None => String::from("-"),
+    };
+    println!(
+        "{}: {} samples, min {}, p50 {}, p90 {}, p99 {}, max {}",
+        name,
+        values.len(),
+        ms(0.0),
+        ms(50.0),
+        ms(90.0),
+        ms(99.0),
+        ms(100.0)
+    );
+}
+
+impl Report {
+    fn new(bots: usize, results: Vec<BotResult>) -> Self {
+        let mut report = Self {
+            bots,
+            connect_times: Vec::new(),
+            round_trips: Vec::new(),
+            pings: Vec::new(),
+            keep_alive_jitter: Vec::new(),
+            stats: Stats::default(),
+            disconnect_reasons: HashMap::new(),
+        };
+
+        for result in results {
+            report.connect_times.extend(result.connect_time);
+            report.round_trips.extend(result.round_trips);
+            report.pings.extend(result.pings);
+            let gaps: Vec<Duration> = result
+                .keep_alives
+                .windows(2)
+                .map(|pair| pair[1] - pair[0])
+                .collect();
+            report.keep_alive_jitter.extend(
+                gaps.windows(2)
+                    .map(|pair| pair[0].max(pair[1]) - pair[0].min(pair[1])),
+            );
+            report.stats.merge(&result.stats);
+            if let Some(reason) = result.disconnect_reason {
+                *report.disconnect_reasons.entry(reason).or_default() += 1;
+            }
+        }
+        report.connect_times.sort();
+        report.round_trips.sort();
+        report.pings.sort();
+        report.keep_alive_jitter.sort();
+
+        report
+    }
+
+    pub fn print(&self) {
+        println!(
+            "{} of {} bots logged in",
+            self.connect_times.len(),
+            self.bots
+        );
+        print_durations("connection time", &self.connect_times);
+        print_durations("keep alive round trip", &self.round_trips);
+        print_durations("server-reported ping", &self.pings);
+        print_durations("keep alive jitter", &self.keep_alive_jitter);
+
+        println!("packets:");
+        self.stats.print();
+
+        let mut reasons: Vec<_> = self.disconnect_reasons.iter().collect();
+        reasons.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        println!("disconnects:");
+        for (reason, count) in reasons {
+            println!("{:6} {}", count, reason);
+        }
+    }
+}
+
+// runs until every bot is done
+pub fn run(options: &LoadTestOptions) -> Report {
+    let mut bots = Vec::new();
+    for number in 0..options.bots {
+        if number > 0 {
+            std::thread::sleep(options.connect_interval);
+        }
+
+        let options = options.clone();
+        bots.push(std::thread::spawn(move || run_bot(&options, number)));
+    }
+
+    let results = bots
+        .into_iter()
+        .map(|bot| {
+            bot.join().unwrap_or_else(|_| BotResult {
+                disconnect_reason: Some(String::from("the bot panicked")),
+                ..BotResult::default()
+            })
+        })
+        .collect();
+    Report::new(options.bots, results)
+}
@@ -1,6 +1,11 @@
-enum State {
+use crate::se::VarInt;
+use serde::{Deserialize, Serialize};
+
+// which set of packets the connection is currently using
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum State {
     Handshake,
-    State,
+    Status,
     Login,
     Play,
     Closed,
@@ -11,3 +16,73 @@ impl Default for State {
         Self::Handshake
     }
 }
+
+impl State {
+    // the next_state field of the Handshake packet
+    pub fn from_next_state(next_state: i32) -> Option<Self> {
+        match next_state {
+            1 => Some(Self::Status),
+            2 => Some(Self::Login),
+            _ => None,
+        }
+    }
+
+    // the state after a packet sent by one side, if it is one that ends this state, see
+    // statemachine.txt
+    // the sender switches as soon as it sends, and the receiver as soon as it reads it, except for a
+    // disconnect: the other side may have sent more packets before reading it
+    pub fn after_packet(self, is_server: bool, packet_id: i32, body: &[u8]) -> Option<Self> {
+        let next = match (self, is_server, packet_id) {
+            (Self::Handshake, false, 0x00) => {
+                #[derive(Deserialize)]
+                struct Handshake<'a> {
+                    _protocol_version: VarInt,
+                    _address: &'a str,
+                    _port: u16,
+                    next_state: VarInt,
+                }
+
+                match crate::se::from_bytes::<Handshake>(body) {
+                    Ok(handshake) => {
+                        Self::from_next_state(handshake.next_state.0).unwrap_or(Self::Closed)
+                    }
+                    Err(_) => Self::Closed,
+                }
+            }
+            (Self::Login, true, 0x00) | (Self::Play, true, 0x19) => Self::Closed,
+            (Self::Login, true, 0x02) => Self::Play,
+            _ => return None,
+        };
+
+        Some(next)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PacketDirection {
+    // sent by the client
+    Serverbound,
+    // sent by the server
+    Clientbound,
+}
+
+impl PacketDirection {
+    pub fn is_server(self) -> bool {
+        self == Self::Clientbound
+    }
+
+    pub fn from_is_server(is_server: bool) -> Self {
+        if is_server {
+            Self::Clientbound
+        } else {
+            Self::Serverbound
+        }
+    }
+
+    pub fn opposite(self) -> Self {
+        match self {
+            Self::Serverbound => Self::Clientbound,
+            Self::Clientbound => Self::Serverbound,
+        }
+    }
+}
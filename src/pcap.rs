@@ -0,0 +1,507 @@
+// Reads Minecraft connections out of .pcap and .pcapng files, such as the ones tcpdump writes
+//
+// Only what is needed to get at the TCP payload is parsed: Ethernet, Linux cooked capture,
+// loopback and raw IP link layers, IPv4 and IPv6, and TCP. Each direction of the connection is
+// reassembled in sequence number order, so retransmitted and out-of-order segments come out once
+// and in the right place.
+
+use crate::se::{Error, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryInto;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+pub const DEFAULT_PORT: u16 = 25565;
+
+// a run of bytes sent in one direction, in the order they were received
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chunk {
+    pub is_server: bool,
+    // since the unix epoch, from the capture
+    pub timestamp: Duration,
+    pub data: Vec<u8>,
+}
+
+fn error<T>(msg: &str) -> Result<T> {
+    Err(Error::Other(format!("pcap: {}", msg)))
+}
+
+// bounds checked reads, so truncated packets are errors instead of panics
+struct Bytes<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl<'a> Bytes<'a> {
+    fn new(data: &'a [u8], little_endian: bool) -> Self {
+        Self {
+            data,
+            little_endian,
+        }
+    }
+
+    fn slice(&self, start: usize, len: usize) -> Result<&'a [u8]> {
+        match self.data.get(start..start.saturating_add(len)) {
+            Some(slice) => Ok(slice),
+            None => error("truncated data"),
+        }
+    }
+
+    fn u16(&self, at: usize) -> Result<u16> {
+        let bytes = self.slice(at, 2)?.try_into().unwrap();
+        Ok(if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+
+    fn u32(&self, at: usize) -> Result<u32> {
+        let bytes = self.slice(at, 4)?.try_into().unwrap();
+        Ok(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+}
+
+// network byte order, for the protocol headers
+fn be16(data: &[u8], at: usize) -> Result<u16> {
+    Bytes::new(data, false).u16(at)
+}
+
+fn be32(data: &[u8], at: usize) -> Result<u32> {
+    Bytes::new(data, false).u32(at)
+}
+
+// a captured packet, still including the link layer header
+struct Frame<'a> {
+    timestamp: Duration,
+    link_type: u32,
+    data: &'a [u8],
+}
+
+const PCAP_MAGIC_MICROS: u32 = 0xA1B2C3D4;
+const PCAP_MAGIC_NANOS: u32 = 0xA1B23C4D;
+const PCAPNG_SECTION_HEADER: u32 = 0x0A0D0D0A;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1A2B3C4D;
+
+fn read_frames(file: &[u8]) -> Result<Vec<Frame<'_>>> {
+    let magic = Bytes::new(file, false).u32(0)?;
+    if magic == PCAPNG_SECTION_HEADER {
+        return read_pcapng(file);
+    }
+
+    let little_endian = match magic {
+        PCAP_MAGIC_MICROS | PCAP_MAGIC_NANOS => false,
+        _ if magic.swap_bytes() == PCAP_MAGIC_MICROS || magic.swap_bytes() == PCAP_MAGIC_NANOS => {
+            true
+        }
+        _ => return error("not a pcap or pcapng file"),
+    };
+    let nanos = magic == PCAP_MAGIC_NANOS || magic.swap_bytes() == PCAP_MAGIC_NANOS;
+
+    let bytes = Bytes::new(file, little_endian);
+    let link_type = bytes.u32(20)? & 0x0FFFFFFF;
+
+    let mut frames = Vec::new();
+    let mut at = 24;
+    while at < file.len() {
+        let seconds = bytes.u32(at)?;
+        let fraction = bytes.u32(at + 4)?;
+        let len = bytes.u32(at + 8)? as usize;
+
+        let fraction = if nanos {
+            Duration::from_nanos(fraction.into())
+        } else {
+            Duration::from_micros(fraction.into())
+        };
+
+        frames.push(Frame {
+            timestamp: Duration::from_secs(seconds.into()) + fraction,
+            link_type,
+            data: bytes.slice(at + 16, len)?,
+        });
+        at += 16 + len;
+    }
+
+    Ok(frames)
+}
+
+struct Interface {
+    link_type: u32,
+    // length of one timestamp unit
+    resolution: Duration,
+}
+
+fn read_pcapng(file: &[u8]) -> Result<Vec<Frame<'_>>> {
+    let mut frames = Vec::new();
+    let mut interfaces: Vec<Interface> = Vec::new();
+    let mut little_endian = false;
+
+    let mut at = 0;
+    while at < file.len() {
+        let block_type = Bytes::new(file, little_endian).u32(at)?;
+
+        if block_type == PCAPNG_SECTION_HEADER {
+            // each section can have a different byte order, and starts over with its interfaces
+            little_endian = match Bytes::new(file, false).u32(at + 8)? {
+                PCAPNG_BYTE_ORDER_MAGIC => false,
+                magic if magic.swap_bytes() == PCAPNG_BYTE_ORDER_MAGIC => true,
+                _ => return error("bad pcapng byte order magic"),
+            };
+            interfaces.clear();
+        }
+
+        let bytes = Bytes::new(file, little_endian);
+        let block_len = bytes.u32(at + 4)? as usize;
+        if block_len < 12 {
+            return error("pcapng block too short");
+        }
+        let body = Bytes::new(bytes.slice(at + 8, block_len - 12)?, little_endian);
+
+        match block_type {
+            // interface description
+            1 => {
+                let mut interface = Interface {
+                    link_type: body.u16(0)?.into(),
+                    resolution: Duration::from_micros(1),
+                };
+
+                // options are a code, a length, and a value padded to 4 bytes
+                let mut option = 8;
+                while option + 4 <= body.data.len() {
+                    let code = body.u16(option)?;
+                    let len = body.u16(option + 2)? as usize;
+                    if code == 0 {
+                        break;
+                    }
+                    if code == 9 && len == 1 {
+                        let resolution = body.slice(option + 4, 1)?[0];
+                        let units_per_second = if resolution & 0x80 == 0 {
+                            10u64.checked_pow(resolution.into())
+                        } else {
+                            2u64.checked_pow((resolution & 0x7F).into())
+                        };
+                        match units_per_second {
+                            Some(units) if units <= 1_000_000_000 => {
+                                interface.resolution = Duration::from_nanos(1_000_000_000 / units);
+                            }
+                            _ => return error("unsupported timestamp resolution"),
+                        }
+                    }
+                    option += 4 + ((len + 3) & !3);
+                }
+
+                interfaces.push(interface);
+            }
+            // enhanced packet
+            6 => {
+                let interface = match interfaces.get(body.u32(0)? as usize) {
+                    Some(interface) => interface,
+                    None => return error("packet from an undescribed interface"),
+                };
+                let units = (u64::from(body.u32(4)?) << 32) | u64::from(body.u32(8)?);
+                let len = body.u32(12)? as usize;
+
+                frames.push(Frame {
+                    timestamp: Duration::from_nanos(
+                        (interface.resolution.as_nanos() as u64).saturating_mul(units),
+                    ),
+                    link_type: interface.link_type,
+                    data: body.slice(20, len)?,
+                });
+            }
+            // simple packet, which has no timestamp
+            3 => {
+                let interface = match interfaces.first() {
+                    Some(interface) => interface,
+                    None => return error("packet from an undescribed interface"),
+                };
+                let len = (body.u32(0)? as usize).min(body.data.len().saturating_sub(4));
+
+                frames.push(Frame {
+                    timestamp: Duration::default(),
+                    link_type: interface.link_type,
+                    data: body.slice(4, len)?,
+                });
+            }
+            // everything else, like name resolution and statistics
+            _ => {}
+        }
+
+        at += block_len;
+    }
+
+    Ok(frames)
+}
+
+// the IP packet inside the link layer, None if it is something else (like ARP)
+fn strip_link_layer(link_type: u32, data: &[u8]) -> Result<Option<&[u8]>> {
+    const ETHERTYPE_IPV4: u16 = 0x0800;
+    const ETHERTYPE_IPV6: u16 = 0x86DD;
+    const ETHERTYPE_VLAN: u16 = 0x8100;
+
+    let (ethertype, header_len) = match link_type {
+        // ethernet, possibly with vlan tags
+        1 => {
+            let mut at = 12;
+            while be16(data, at)? == ETHERTYPE_VLAN {
+                at += 4;
+            }
+            (be16(data, at)?, at + 2)
+        }
+        // linux cooked capture v1 and v2
+        113 => (be16(data, 14)?, 16),
+        276 => (be16(data, 0)?, 20),
+        // bsd loopback, the address family is in host byte order
+        0 | 108 => {
+            let family = Bytes::new(data, true).u32(0)?;
+            let family = if family > 0xFFFF {
+                family.swap_bytes()
+            } else {
+                family
+            };
+            match family {
+                2 => (ETHERTYPE_IPV4, 4),
+                24 | 28 | 30 => (ETHERTYPE_IPV6, 4),
+                _ => return Ok(None),
+            }
+        }
+        // raw ip
+        12 | 14 | 101 => match data.first().map(|byte| byte >> 4) {
+            Some(4) => (ETHERTYPE_IPV4, 0),
+            Some(6) => (ETHERTYPE_IPV6, 0),
+            _ => return Ok(None),
+        },
+        _ => return error(&format!("unsupported link type {}", link_type)),
+    };
+
+    match ethertype {
+        ETHERTYPE_IPV4 | ETHERTYPE_IPV6 => match data.get(header_len..) {
+            Some(ip) => Ok(Some(ip)),
+            None => error("truncated data"),
+        },
+        _ => Ok(None),
+    }
+}
+
+struct Segment<'a> {
+    src: SocketAddr,
+    dst: SocketAddr,
+    seq: u32,
+    syn: bool,
+    payload: &'a [u8],
+}
+
+const TCP: u8 = 6;
+
+// the TCP segment inside the IP packet, None if it is something else (like UDP)
+fn parse_ip(data: &[u8]) -> Result<Option<Segment<'_>>> {
+    let (src, dst, tcp) = match data.first().map(|byte| byte >> 4) {
+        Some(4) => {
+            let header_len = usize::from(data[0] & 0x0F) * 4;
+            // the total length excludes any ethernet padding
+            let total_len = usize::from(be16(data, 2)?);
+            let fragment = be16(data, 6)? & 0x3FFF;
+            if data.get(9) != Some(&TCP) || fragment != 0 {
+                return Ok(None);
+            }
+
+            let address = |at| -> Result<IpAddr> {
+                let octets: [u8; 4] = Bytes::new(data, false).slice(at, 4)?.try_into().unwrap();
+                Ok(Ipv4Addr::from(octets).into())
+            };
+            let tcp =
+                Bytes::new(data, false).slice(header_len, total_len.saturating_sub(header_len))?;
+            (address(12)?, address(16)?, tcp)
+        }
+        Some(6) => {
+            let payload_len = usize::from(be16(data, 4)?);
+            let mut next_header = *Bytes::new(data, false).slice(6, 1)?.first().unwrap();
+            let mut at = 40;
+
+            // skip hop-by-hop, routing and destination options extension headers
+            while let 0 | 43 | 60 = next_header {
+                next_header = Bytes::new(data, false).slice(at, 1)?[0];
+                at += (usize::from(Bytes::new(data, false).slice(at + 1, 1)?[0]) + 1) * 8;
+            }
+            if next_header != TCP {
+                return Ok(None);
+            }
+
+            let address = |at| -> Result<IpAddr> {
+                let octets: [u8; 16] = Bytes::new(data, false).slice(at, 16)?.try_into().unwrap();
+                Ok(Ipv6Addr::from(octets).into())
+            };
+            let tcp = Bytes::new(data, false).slice(at, (40 + payload_len).saturating_sub(at))?;
+            (address(8)?, address(24)?, tcp)
+        }
+        _ => return Ok(None),
+    };
+
+    let header_len = usize::from(Bytes::new(tcp, false).slice(12, 1)?[0] >> 4) * 4;
+    let flags = Bytes::new(tcp, false).slice(13, 1)?[0];
+
+    Ok(Some(Segment {
+        src: SocketAddr::new(src, be16(tcp, 0)?),
+        dst: SocketAddr::new(dst, be16(tcp, 2)?),
+        seq: be32(tcp, 4)?,
+        syn: flags & 0x02 != 0,
+        payload: Bytes::new(tcp, false).slice(header_len, tcp.len().saturating_sub(header_len))?,
+    }))
+}
+
+// one direction of the connection
+#[derive(Default)]
+struct Stream {
+    // sequence number of the first byte of data
+    base: Option<u32>,
+    // how many bytes have been put in order so far
+    assembled: u64,
+    // segments that arrived before the ones in front of them, by offset from base
+    pending: BTreeMap<u64, Vec<u8>>,
+}
+
+impl Stream {
+    // returns whatever data is now in order
+    fn add(&mut self, seq: u32, syn: bool, payload: &[u8]) -> Vec<u8> {
+        // the SYN takes up one sequence number before the data
+        let base = *self
+            .base
+            .get_or_insert_with(|| if syn { seq.wrapping_add(1) } else { seq });
+        if payload.is_empty() {
+            return Vec::new();
+        }
+
+        // sequence numbers wrap every 4 GiB, so the offset is extended to 64 bits by how far seq is
+        // from the next byte expected, which is negative for data that was already seen
+        let next = base.wrapping_add(self.assembled as u32);
+        let offset = self.assembled as i64 + i64::from(seq.wrapping_sub(next) as i32);
+
+        // the part from before base was sent before the data started
+        let (offset, payload) = if offset < 0 {
+            let skip = offset.unsigned_abs().min(payload.len() as u64) as usize;
+            (0, &payload[skip..])
+        } else {
+            (offset as u64, payload)
+        };
+        if payload.is_empty() {
+            return Vec::new();
+        }
+
+        if offset > self.assembled {
+            self.pending
+                .entry(offset)
+                .or_insert_with(|| payload.to_vec());
+            return Vec::new();
+        }
+
+        let mut output = Vec::new();
+        self.append(offset, payload, &mut output);
+
+        // fill in anything that was waiting on this
+        while let Some((&offset, _)) = self.pending.iter().next() {
+            if offset > self.assembled {
+                break;
+            }
+            let payload = self.pending.remove(&offset).unwrap();
+            self.append(offset, &payload, &mut output);
+        }
+
+        output
+    }
+
+    // skips the part that was already seen, which is all of it for a retransmission
+    fn append(&mut self, offset: u64, payload: &[u8], output: &mut Vec<u8>) {
+        let end = offset + payload.len() as u64;
+        if end <= self.assembled {
+            return;
+        }
+
+        let skip = (self.assembled - offset) as usize;
+        output.extend_from_slice(&payload[skip..]);
+        self.assembled = end;
+    }
+}
+
+// Reassembles the first Minecraft connection in the capture.
+// port is the server's port, by default 25565 if anything uses it, otherwise whatever the first
+// connection attempt (SYN) went to.
+pub fn read_connection(file: &[u8], port: Option<u16>) -> Result<Vec<Chunk>> {
+    let mut segments = Vec::new();
+    for frame in read_frames(file)? {
+        let ip = match strip_link_layer(frame.link_type, frame.data)? {
+            Some(ip) => ip,
+            None => continue,
+        };
+        if let Some(segment) = parse_ip(ip)? {
+            segments.push((frame.timestamp, segment));
+        }
+    }
+
+    let port = port.unwrap_or_else(|| {
+        let uses = |port: u16| {
+            segments
+                .iter()
+                .any(|(_, segment)| segment.src.port() == port || segment.dst.port() == port)
+        };
+        let first_syn = segments
+            .iter()
+            .find(|(_, segment)| segment.syn && segment.dst.port() != DEFAULT_PORT)
+            .map(|(_, segment)| segment.dst.port());
+
+        match first_syn {
+            Some(port) if !uses(DEFAULT_PORT) => port,
+            _ => DEFAULT_PORT,
+        }
+    });
+
+    // the first client to talk to that port
+    let client = match segments.iter().find_map(|(_, segment)| {
+        if segment.dst.port() == port {
+            Some(segment.src)
+        } else if segment.src.port() == port {
+            Some(segment.dst)
+        } else {
+            None
+        }
+    }) {
+        Some(client) => client,
+        None => return error(&format!("no TCP traffic on port {}", port)),
+    };
+
+    let mut streams: HashMap<bool, Stream> = HashMap::new();
+    let mut output: Vec<Chunk> = Vec::new();
+
+    for (timestamp, segment) in segments {
+        let is_server = if segment.dst == client && segment.src.port() == port {
+            true
+        } else if segment.src == client && segment.dst.port() == port {
+            false
+        } else {
+            continue;
+        };
+
+        let data =
+            streams
+                .entry(is_server)
+                .or_default()
+                .add(segment.seq, segment.syn, segment.payload);
+        if data.is_empty() {
+            continue;
+        }
+
+        match output.last_mut() {
+            Some(chunk) if chunk.is_server == is_server => chunk.data.extend(data),
+            _ => output.push(Chunk {
+                is_server,
+                timestamp,
+                data,
+            }),
+        }
+    }
+
+    Ok(output)
+}
@@ -0,0 +1,596 @@
+// A proxy that sits between a client and a server, decoding and recording what both of them send
+//
+// Each client that connects gets its own connection to the upstream server, and a thread reading
+// from each side. Both threads share a Session behind a mutex, which splits what was read into
+// frames, follows the connection state, records and prints the frames, and sends them on to the
+// other side. Since everything goes through the one Session, compression and encryption are
+// switched on at the same packet in both directions.
+//
+// Online mode servers send an Encryption Request with their public key, for the client to encrypt
+// the shared secret with. The proxy passes its own key to the client instead, so it can read the
+// secret, and passes the secret on encrypted with the server's key. Both sides end up with the
+// same secret, which is saved next to the capture so packet_inspector can also decrypt a pcap of
+// the session. The client reports the proxy's key when it joins the session, so servers that check
+// the session with Mojang will kick it: online mode only works against servers that trust the
+// proxy, like a local test server that does not verify sessions.
+//
+// Hooks see each packet before it is sent on, and can change it, drop it, send other packets with
+// it, or hold it back for a while. Sending is done by a thread for each side, so a delayed packet
+// holds up the packets after it in the same direction, but not the other direction or the hooks.
+
+use crate::capture::CaptureWriter;
+use crate::codec::{split_packet_id, Decoder, Encoder};
+use crate::inspector::{do_one_data, packet_name, Data, Inspector, Options};
+use crate::logic::{PacketDirection, State};
+use crate::se::{self, Error, Result, VarInt};
+use rsa::pkcs8::{DecodePublicKey, EncodePublicKey};
+use rsa::{Pkcs1v15Encrypt, RsaPrivateKey, RsaPublicKey};
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// the size the vanilla server uses
+const KEY_BITS: usize = 1024;
+
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+struct EncryptionRequest {
+    server_id: String,
+    public_key: Vec<u8>,
+    verify_token: Vec<u8>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+struct EncryptionResponse {
+    shared_secret: Vec<u8>,
+    verify_token: Vec<u8>,
+}
+
+fn key_error(err: impl std::fmt::Display) -> Error {
+    Error::Other(format!("key exchange: {}", err))
+}
+
+// the key pair the proxy gives clients, made the first time a server asks for encryption
+struct Keys {
+    private: RsaPrivateKey,
+    // in the DER format the protocol uses
+    public: Vec<u8>,
+}
+
+impl Keys {
+    fn new() -> Result<Self> {
+        let private = RsaPrivateKey::new(&mut rand::rngs::OsRng, KEY_BITS).map_err(key_error)?;
+        let public = RsaPublicKey::from(&private)
+            .to_public_key_der()
+            .map_err(key_error)?
+            .into_vec();
+
+        Ok(Self { private, public })
+    }
+}
+
+// a packet on its way through the proxy
+#[derive(Clone, Debug, PartialEq)]
+pub struct Packet {
+    pub direction: PacketDirection,
+    pub state: State,
+    pub id: i32,
+    pub body: Vec<u8>,
+}
+
+pub type ClientboundPacket = Packet;
+pub type ServerboundPacket = Packet;
+
+impl Packet {
+    pub fn new<T: serde::Serialize>(
+        direction: PacketDirection,
+        state: State,
+        id: i32,
+        data: &T,
+    ) -> Result<Self> {
+        Ok(Self {
+            direction,
+            state,
+            id,
+            body: se::serialize(data)?,
+        })
+    }
+
+    pub fn name(&self) -> Option<&'static str> {
+        packet_name(self.state, self.direction.is_server(), self.id)
+    }
+
+    // the whole body has to be read, so that writing the packet back does not lose the rest of it
+    pub fn read<'a, T: serde::Deserialize<'a>>(&'a self) -> Result<T> {
+        se::from_bytes_exact(&self.body)
+    }
+
+    pub fn write<T: serde::Serialize>(&mut self, data: &T) -> Result<()> {
+        self.body = se::serialize(data)?;
+        Ok(())
+    }
+
+    fn to_frame(&self) -> Result<Vec<u8>> {
+        let mut frame = se::serialize(VarInt(self.id))?;
+        frame.extend_from_slice(&self.body);
+        Ok(frame)
+    }
+}
+
+// what to do with a packet after a hook has seen it
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    // send it on, with any changes the hook made
+    Forward,
+    // dont send it, and dont show it to the hooks after this one
+    Drop,
+    // send it on, followed by these packets, each to the side it is addressed to
+    Inject(Vec<Packet>),
+    // send it on after this long
+    Delay(Duration),
+}
+
+// Changes the packets going through a session. Each session gets its own hooks, and a packet goes
+// through them in order, each seeing the changes of the ones before. The packets that set up
+// encryption and compression are handled by the proxy, and never reach the hooks.
+pub trait Hook: Send {
+    fn on_clientbound(&mut self, _packet: &mut ClientboundPacket) -> Action {
+        Action::Forward
+    }
+
+    fn on_serverbound(&mut self, _packet: &mut ServerboundPacket) -> Action {
+        Action::Forward
+    }
+}
+
+// makes the hooks for each new session
+pub type HookFactory = Box<dyn Fn() -> Box<dyn Hook> + Send + Sync>;
+
+// delays every packet in the play state by the same amount each way, to simulate a slow connection
+pub struct Lag(pub Duration);
+
+impl Lag {
+    fn action(&self, packet: &Packet) -> Action {
+        if packet.state == State::Play {
+            Action::Delay(self.0)
+        } else {
+            Action::Forward
+        }
+    }
+}
+
+impl Hook for Lag {
+    fn on_clientbound(&mut self, packet: &mut ClientboundPacket) -> Action {
+        self.action(packet)
+    }
+
+    fn on_serverbound(&mut self, packet: &mut ServerboundPacket) -> Action {
+        self.action(packet)
+    }
+}
+
+// drops packets with these names (like ChunkData), compared ignoring case
+pub struct DropPackets(pub Vec<String>);
+
+impl DropPackets {
+    fn action(&self, packet: &Packet) -> Action {
+        let name = match packet.name() {
+            Some(name) => name,
+            None => return Action::Forward,
+        };
+
+        if self.0.iter().any(|drop| drop.eq_ignore_ascii_case(name)) {
+            Action::Drop
+        } else {
+            Action::Forward
+        }
+    }
+}
+
+impl Hook for DropPackets {
+    fn on_clientbound(&mut self, packet: &mut ClientboundPacket) -> Action {
+        self.action(packet)
+    }
+
+    fn on_serverbound(&mut self, packet: &mut ServerboundPacket) -> Action {
+        self.action(packet)
+    }
+}
+
+pub struct ProxyOptions {
+    // address to accept clients on
+    pub listen: String,
+    // address of the server to connect them to
+    pub upstream: String,
+    // where to write a capture (and the shared secret, if encrypted) of each session
+    pub log_dir: Option<PathBuf>,
+    // what to print for each packet, or None to print only when sessions start and end
+    pub inspector: Option<Options>,
+    pub hooks: Vec<HookFactory>,
+}
+
+// encoded packets, and when to send them
+type Queue = Sender<(Instant, Vec<u8>)>;
+
+// one client and its connection to the server
+struct Session {
+    // names the session in messages and log files
+    name: String,
+    state: State,
+    from_client: Decoder,
+    to_server: Encoder,
+    from_server: Decoder,
+    to_client: Encoder,
+    client: TcpStream,
+    server: TcpStream,
+    to_client_queue: Queue,
+    to_server_queue: Queue,
+    hooks: Vec<Box<dyn Hook>>,
+    capture: Option<CaptureWriter<BufWriter<File>>>,
+    inspector: Option<Inspector>,
+    log_dir: Option<PathBuf>,
+    keys: Arc<OnceLock<Keys>>,
+    // the server's key, from its Encryption Request
+    server_key: Option<RsaPublicKey>,
+}
+
+impl Session {
+    fn log_path(&self, extension: &str) -> Option<PathBuf> {
+        self.log_dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{}.{}", self.name, extension)))
+    }
+
+    // the capture is started by the first packet, so it can have the protocol version from the
+    // handshake. It is flushed before the packet is passed on, so whoever gets it can already find
+    // it in the capture
+    fn record(&mut self, is_server: bool, frame: &[u8]) -> Result<()> {
+        if self.capture.is_none() {
+            if let Some(path) = self.log_path("digcap") {
+                let protocol_version = match split_packet_id(frame) {
+                    Ok((0x00, body)) if self.state == State::Handshake => {
+                        VarInt::_parse(&mut &body[..]).ok()
+                    }
+                    _ => None,
+                };
+                let file = BufWriter::new(File::create(path)?);
+                self.capture = Some(CaptureWriter::new(file, protocol_version)?);
+            }
+        }
+
+        if let Some(capture) = &mut self.capture {
+            capture.write(
+                PacketDirection::from_is_server(is_server),
+                self.state,
+                frame,
+            )?;
+            capture.flush()?;
+        }
+
+        if let Some(inspector) = &mut self.inspector {
+            let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).ok();
+            // a packet the inspector cant decode is still forwarded
+            let data = Data::frame(is_server, frame.to_vec(), timestamp);
+            if let Err(err) = do_one_data(&data, inspector) {
+                eprintln!("{}: could not decode packet: {}", self.name, err);
+            }
+        }
+
+        Ok(())
+    }
+
+    // sends the client the proxy's key instead of the server's
+    fn encryption_request(&mut self, body: &[u8]) -> Result<Vec<u8>> {
+        let mut packet: EncryptionRequest = se::from_bytes(body)?;
+        self.server_key =
+            Some(RsaPublicKey::from_public_key_der(&packet.public_key).map_err(key_error)?);
+
+        let keys = match self.keys.get() {
+            Some(keys) => keys,
+            None => {
+                let keys = Keys::new()?;
+                self.keys.get_or_init(|| keys)
+            }
+        };
+        packet.public_key = keys.public.clone();
+
+        let mut frame = se::serialize(VarInt(0x01))?;
+        se::serialize_into(&mut frame, &packet)?;
+        Ok(frame)
+    }
+
+    // reads the shared secret with the proxy's key, and sends it on with the server's key
+    fn encryption_response(&mut self, body: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+        let packet: EncryptionResponse = se::from_bytes(body)?;
+        let (keys, server_key) = match (self.keys.get(), &self.server_key) {
+            (Some(keys), Some(server_key)) => (keys, server_key),
+            _ => Err(Error::Packet(String::from(
+                "Encryption Response without an Encryption Request",
+            )))?,
+        };
+
+        let secret = keys
+            .private
+            .decrypt(Pkcs1v15Encrypt, &packet.shared_secret)
+            .map_err(key_error)?;
+        let verify_token = keys
+            .private
+            .decrypt(Pkcs1v15Encrypt, &packet.verify_token)
+            .map_err(key_error)?;
+
+        let mut rng = rand::rngs::OsRng;
+        let packet = EncryptionResponse {
+            shared_secret: server_key
+                .encrypt(&mut rng, Pkcs1v15Encrypt, &secret)
+                .map_err(key_error)?,
+            verify_token: server_key
+                .encrypt(&mut rng, Pkcs1v15Encrypt, &verify_token)
+                .map_err(key_error)?,
+        };
+
+        let mut frame = se::serialize(VarInt(0x01))?;
+        se::serialize_into(&mut frame, &packet)?;
+        Ok((frame, secret))
+    }
+
+    fn enable_encryption(&mut self, secret: &[u8]) -> Result<()> {
+        self.from_client.enable_encryption(secret)?;
+        self.to_server.enable_encryption(secret)?;
+        self.from_server.enable_encryption(secret)?;
+        self.to_client.enable_encryption(secret)?;
+
+        if let Some(path) = self.log_path("secret") {
+            std::fs::write(path, hex::encode(secret))?;
+        }
+
+        Ok(())
+    }
+
+    fn set_compression(&mut self, threshold: i32) {
+        self.from_client.set_compression(threshold);
+        self.to_server.set_compression(threshold);
+        self.from_server.set_compression(threshold);
+        self.to_client.set_compression(threshold);
+    }
+
+    fn send(&mut self, direction: PacketDirection, frame: &[u8], at: Instant) -> Result<()> {
+        let (encoder, queue) = match direction {
+            PacketDirection::Clientbound => (&mut self.to_client, &self.to_client_queue),
+            PacketDirection::Serverbound => (&mut self.to_server, &self.to_server_queue),
+        };
+
+        let output = encoder.encode(frame)?;
+        queue
+            .send((at, output))
+            .map_err(|_| Error::Other(String::from("connection is closed")))
+    }
+
+    fn run_hooks(&mut self, mut packet: Packet) -> Result<()> {
+        let now = Instant::now();
+        let mut delay = Duration::from_secs(0);
+        let mut injected = Vec::new();
+        let mut dropped = false;
+
+        for hook in &mut self.hooks {
+            let action = match packet.direction {
+                PacketDirection::Clientbound => hook.on_clientbound(&mut packet),
+                PacketDirection::Serverbound => hook.on_serverbound(&mut packet),
+            };
+            match action {
+                Action::Forward => {}
+                Action::Drop => {
+                    dropped = true;
+                    break;
+                }
+                Action::Inject(packets) => injected.extend(packets),
+                Action::Delay(time) => delay += time,
+            }
+        }
+
+        if !dropped {
+            self.send(packet.direction, &packet.to_frame()?, now + delay)?;
+        }
+        for packet in injected {
+            self.send(packet.direction, &packet.to_frame()?, now)?;
+        }
+
+        Ok(())
+    }
+
+    fn frame(&mut self, is_server: bool, frame: Vec<u8>) -> Result<()> {
+        let (packet_id, body) = split_packet_id(&frame)?;
+        let state = self.state;
+        let direction = PacketDirection::from_is_server(is_server);
+
+        // the capture has the packets as they were sent, before the keys are swapped or the hooks
+        // change them
+        self.record(is_server, &frame)?;
+
+        let mut secret = None;
+        match (state, is_server, packet_id) {
+            (State::Login, true, 0x01) => {
+                let forward = self.encryption_request(body)?;
+                self.send(direction, &forward, Instant::now())?;
+            }
+            (State::Login, false, 0x01) => {
+                let (forward, shared_secret) = self.encryption_response(body)?;
+                self.send(direction, &forward, Instant::now())?;
+                secret = Some(shared_secret);
+            }
+            (State::Login, true, 0x03) => self.send(direction, &frame, Instant::now())?,
+            _ => self.run_hooks(Packet {
+                direction,
+                state,
+                id: packet_id,
+                body: body.to_vec(),
+            })?,
+        }
+
+        // like the state, both sides switch after the packet that turns these on
+        if let Some(secret) = secret {
+            self.enable_encryption(&secret)?;
+        }
+        if (state, is_server, packet_id) == (State::Login, true, 0x03) {
+            let threshold: VarInt = se::from_bytes(body)?;
+            self.set_compression(threshold.0);
+        }
+        if let Some(next) = state.after_packet(is_server, packet_id, body) {
+            self.state = next;
+        }
+
+        Ok(())
+    }
+
+    fn received(&mut self, is_server: bool, data: &[u8]) -> Result<()> {
+        let decoder = if is_server {
+            &mut self.from_server
+        } else {
+            &mut self.from_client
+        };
+        decoder.push(data);
+
+        loop {
+            let decoder = if is_server {
+                &mut self.from_server
+            } else {
+                &mut self.from_client
+            };
+            match decoder.next_frame()? {
+                Some(frame) => self.frame(is_server, frame)?,
+                None => break,
+            }
+        }
+
+        Ok(())
+    }
+
+    // closing both sockets also stops the thread reading the other side
+    fn close(&mut self) {
+        let _ = self.client.shutdown(Shutdown::Both);
+        let _ = self.server.shutdown(Shutdown::Both);
+        if let Some(capture) = &mut self.capture {
+            let _ = capture.flush();
+        }
+    }
+}
+
+// sends the packets for one side when their time comes, until the session is gone
+fn write_queued(queue: Receiver<(Instant, Vec<u8>)>, mut writer: TcpStream) {
+    for (at, data) in queue {
+        let now = Instant::now();
+        if at > now {
+            std::thread::sleep(at - now);
+        }
+        if writer.write_all(&data).is_err() {
+            break;
+        }
+    }
+}
+
+// reads everything from one side until either side closes
+fn pump(session: &Mutex<Session>, mut reader: TcpStream, is_server: bool) {
+    let mut buffer = vec![0; 1 << 16];
+
+    loop {
+        let len = match reader.read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(len) => len,
+        };
+
+        let mut session = session.lock().unwrap();
+        if let Err(err) = session.received(is_server, &buffer[..len]) {
+            eprintln!(
+                "{}: error in packet from the {}: {}",
+                session.name,
+                if is_server { "server" } else { "client" },
+                err
+            );
+            break;
+        }
+    }
+
+    session.lock().unwrap().close();
+}
+
+fn start_session(
+    name: String,
+    client: TcpStream,
+    options: &ProxyOptions,
+    keys: &Arc<OnceLock<Keys>>,
+) -> Result<()> {
+    let server = TcpStream::connect(&options.upstream)?;
+    client.set_nodelay(true)?;
+    server.set_nodelay(true)?;
+
+    let (to_client_queue, to_client) = channel();
+    let (to_server_queue, to_server) = channel();
+    let client_writer = client.try_clone()?;
+    let server_writer = server.try_clone()?;
+    std::thread::spawn(move || write_queued(to_client, client_writer));
+    std::thread::spawn(move || write_queued(to_server, server_writer));
+
+    let session = Session {
+        name: name.clone(),
+        state: State::default(),
+        from_client: Decoder::new(),
+        to_server: Encoder::new(),
+        from_server: Decoder::new(),
+        to_client: Encoder::new(),
+        client: client.try_clone()?,
+        server: server.try_clone()?,
+        to_client_queue,
+        to_server_queue,
+        hooks: options.hooks.iter().map(|make_hook| make_hook()).collect(),
+        capture: None,
+        inspector: options.inspector.clone().map(Inspector::new),
+        log_dir: options.log_dir.clone(),
+        keys: keys.clone(),
+        server_key: None,
+    };
+    let session = Arc::new(Mutex::new(session));
+
+    let server_session = session.clone();
+    std::thread::spawn(move || pump(&server_session, server, true));
+    std::thread::spawn(move || {
+        pump(&session, client, false);
+        eprintln!("{}: closed", name);
+    });
+
+    Ok(())
+}
+
+// accepts clients until the listener fails
+pub fn run(options: ProxyOptions) -> Result<()> {
+    let listener = TcpListener::bind(&options.listen)?;
+    let keys = Arc::new(OnceLock::new());
+    if let Some(dir) = &options.log_dir {
+        std::fs::create_dir_all(dir)?;
+    }
+    let started = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or(0);
+    eprintln!(
+        "forwarding {} to {}",
+        listener.local_addr()?,
+        options.upstream
+    );
+
+    for (i, client) in listener.incoming().enumerate() {
+        let client = client?;
+        let name = format!("{}-{}", started, i);
+        eprintln!("{}: {} connected", name, client.peer_addr()?);
+
+        if let Err(err) = start_session(name.clone(), client, &options, &keys) {
+            eprintln!(
+                "{}: could not connect to {}: {}",
+                name, options.upstream, err
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,271 @@
+// A server that plays a recorded session back to a real client, to reproduce what it was sent
+//
+// The client goes through the handshake and an offline mode login without compression, and is
+// then sent the clientbound play packets of the capture, with the same time between them as when
+// they were recorded. What the client sends is read and thrown away, or printed. Once the
+// recording runs out, the client is sent keep alives so it stays connected until it leaves.
+
+use crate::capture::{CaptureReader, Record};
+use crate::chat::{Json, StatusPlayers, StatusResponse, StatusVersion, TextComponent};
+use crate::inspector::packet_name;
+use crate::logic::{PacketDirection, State};
+use crate::se::uuid::offline_player_uuid;
+use crate::se::{self, Error, Result, VarInt};
+use crate::{read_packeta, write_packet};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_bytes::Bytes;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// the vanilla server sends one every 15 seconds, and the client gives up after 30
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Deserialize, Debug)]
+struct Handshake {
+    protocol_version: VarInt,
+    _address: String,
+    _port: u16,
+    next_state: VarInt,
+}
+
+#[derive(Deserialize, Debug)]
+struct Request {}
+
+#[derive(Deserialize, Debug)]
+struct LoginStart {
+    username: String,
+}
+
+#[derive(Serialize, Debug)]
+struct LoginSuccess<'a> {
+    uuid: se::Uuid,
+    username: &'a str,
+}
+
+#[derive(Serialize, Debug)]
+struct Disconnect {
+    reason: Json<TextComponent>,
+}
+
+// the part of a capture that gets replayed
+pub struct Recording {
+    pub protocol_version: Option<i32>,
+    // the clientbound packets in the play state, in order
+    pub packets: Vec<Record>,
+}
+
+impl Recording {
+    pub fn read<R: BufRead>(reader: R) -> Result<Self> {
+        let mut reader = CaptureReader::new(reader)?;
+        let protocol_version = match reader.header().protocol_version.0 {
+            -1 => None,
+            protocol_version => Some(protocol_version),
+        };
+
+        let mut packets = Vec::new();
+        for record in reader.by_ref() {
+            let record = record?;
+            if record.direction == PacketDirection::Clientbound && record.state == State::Play {
+                packets.push(record);
+            }
+        }
+
+        Ok(Self {
+            protocol_version,
+            packets,
+        })
+    }
+}
+
+pub struct ReplayOptions {
+    // address to accept clients on
+    pub listen: String,
+    // print the packets sent by clients
+    pub log_input: bool,
+}
+
+fn read_expected<R, T>(reader: &mut R, expected_id: i32) -> Result<T>
+where
+    R: BufRead,
+    T: DeserializeOwned,
+{
+    let mut packet = None;
+    let packet_id = read_packeta(reader, |packet_id, body| {
+        if packet_id == expected_id {
+            packet = Some(se::from_bytes(body)?);
+        }
+        Ok(())
+    })?;
+
+    packet.ok_or_else(|| {
+        Error::Packet(format!(
+            "expected packet 0x{:02X}, got 0x{:02X}",
+            expected_id, packet_id
+        ))
+    })
+}
+
+// answers a server list ping, so the replay shows up in the client's server list
+fn status<R: BufRead, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    recording: &Recording,
+    protocol_version: i32,
+) -> Result<()> {
+    let _: Request = read_expected(reader, 0x00)?;
+
+    let response = StatusResponse {
+        version: StatusVersion {
+            name: String::from("digmake replay"),
+            protocol: recording.protocol_version.unwrap_or(protocol_version),
+        },
+        players: StatusPlayers {
+            max: 1,
+            online: 0,
+            sample: Vec::new(),
+        },
+        description: TextComponent::text(format!("replay of {} packets", recording.packets.len())),
+        favicon: None,
+    };
+    write_packet(&mut *writer, 0x00, &Json(response))?;
+    writer.flush()?;
+
+    let payload: i64 = read_expected(reader, 0x01)?;
+    write_packet(&mut *writer, 0x01, &payload)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+// reads what the client sends until it leaves
+fn read_input<R: BufRead>(mut reader: R, name: &str, log_input: bool) {
+    loop {
+        let result = read_packeta(&mut reader, |packet_id, body| {
+            if log_input {
+                println!(
+                    "{}: client sent Play 0x{:02X} {} ({} bytes)",
+                    name,
+                    packet_id,
+                    packet_name(State::Play, false, packet_id).unwrap_or("unknown"),
+                    body.len()
+                );
+            }
+            Ok(())
+        });
+        if result.is_err() {
+            break;
+        }
+    }
+}
+
+fn replay<W: Write>(writer: &mut W, recording: &Recording, name: &str) -> Result<()> {
+    let start = Instant::now();
+    let first = match recording.packets.first() {
+        Some(record) => record.timestamp(),
+        None => Duration::from_secs(0),
+    };
+
+    for record in &recording.packets {
+        // a capture put together out of order can go back in time, those packets are sent at once
+        let at = start + record.timestamp().saturating_sub(first);
+        let now = Instant::now();
+        if at > now {
+            std::thread::sleep(at - now);
+        }
+
+        // the body is written as is, after the length and id
+        write_packet(
+            &mut *writer,
+            record.packet_id()?,
+            &Bytes::new(record.body()?),
+        )?;
+        writer.flush()?;
+    }
+    eprintln!("{}: replayed {} packets", name, recording.packets.len());
+
+    for keep_alive in 0i64.. {
+        std::thread::sleep(KEEP_ALIVE_INTERVAL);
+        write_packet(&mut *writer, 0x1F, &keep_alive)?;
+        writer.flush()?;
+    }
+
+    Ok(())
+}
+
+fn serve(client: TcpStream, recording: &Recording, name: &str, log_input: bool) -> Result<()> {
+    let mut reader = BufReader::new(client.try_clone()?);
+    let mut writer = BufWriter::new(client);
+
+    let handshake: Handshake = read_expected(&mut reader, 0x00)?;
+    let protocol_version = handshake.protocol_version.0;
+    match State::from_next_state(handshake.next_state.0) {
+        Some(State::Status) => {
+            return status(&mut reader, &mut writer, recording, protocol_version)
+        }
+        Some(State::Login) => {}
+        _ => Err(Error::Packet(format!(
+            "invalid next state {}",
+            handshake.next_state.0
+        )))?,
+    }
+
+    let login_start: LoginStart = read_expected(&mut reader, 0x00)?;
+    if let Some(recorded) = recording.protocol_version {
+        if recorded != protocol_version {
+            let reason = format!(
+                "This recording is for protocol version {}, not {}",
+                recorded, protocol_version
+            );
+            let packet = Disconnect {
+                reason: Json(TextComponent::text(reason)),
+            };
+            write_packet(&mut writer, 0x00, &packet)?;
+            writer.flush()?;
+            return Ok(());
+        }
+    }
+
+    let packet = LoginSuccess {
+        uuid: se::Uuid(offline_player_uuid(&login_start.username)),
+        username: &login_start.username,
+    };
+    write_packet(&mut writer, 0x02, &packet)?;
+    writer.flush()?;
+    eprintln!("{}: {} logged in", name, login_start.username);
+
+    let input_name = name.to_owned();
+    std::thread::spawn(move || read_input(reader, &input_name, log_input));
+
+    replay(&mut writer, recording, name)
+}
+
+// accepts clients until the listener fails, replaying the recording to each of them
+pub fn run(recording: Recording, options: ReplayOptions) -> Result<()> {
+    let listener = TcpListener::bind(&options.listen)?;
+    let recording = Arc::new(recording);
+    eprintln!(
+        "replaying {} packets on {}",
+        recording.packets.len(),
+        listener.local_addr()?
+    );
+
+    for (i, client) in listener.incoming().enumerate() {
+        let client = client?;
+        let name = format!("client {}", i);
+        eprintln!("{}: {} connected", name, client.peer_addr()?);
+
+        let recording = recording.clone();
+        let log_input = options.log_input;
+        std::thread::spawn(move || {
+            match serve(client, &recording, &name, log_input) {
+                Ok(()) => eprintln!("{}: closed", name),
+                Err(err) => eprintln!("{}: closed: {}", name, err),
+            };
+        });
+    }
+
+    Ok(())
+}
@@ -1,4 +1,7 @@
-use super::{error::DeError, varnum};
+use super::{
+    error::{DeError, Diagnostic, PathSegment},
+    varnum, DeserializerConfig,
+};
 use crate::se::{
     error::{Error, Result},
     mon::{self, Parse, ParseB},
@@ -7,40 +10,106 @@ use crate::se::{
 use nom::number::complete as nom_num;
 use nom::Finish;
 use serde::de::{
-    DeserializeSeed, Deserializer as SDeserializer, EnumAccess, SeqAccess, VariantAccess, Visitor,
+    DeserializeSeed, Deserializer as SDeserializer, EnumAccess, MapAccess, SeqAccess,
+    VariantAccess, Visitor,
 };
 
 pub(super) struct Deserializer<'de> {
-    #[allow(dead_code)]
     pub(super) original_input: Input<'de>,
     pub(super) input: Input<'de>,
+    // which field or element is being deserialized, for error messages
+    // segments are only popped on success, so after an error this points to where it happened
+    pub(super) path: Vec<PathSegment>,
+    config: DeserializerConfig,
 }
 
 impl<'de> Deserializer<'de> {
     pub(super) fn new(input: Input<'de>) -> Self {
+        Self::with_config(input, DeserializerConfig::default())
+    }
+
+    pub(super) fn with_config(input: Input<'de>, config: DeserializerConfig) -> Self {
         Self {
             original_input: input,
             input,
+            path: Vec::new(),
+            config,
         }
     }
 
     fn update<T>(&mut self, result: mon::Result<'de, T>) -> Result<T> {
-        let parsed = result.finish()?;
+        let parsed = result.finish().map_err(|err| {
+            let (offset, len) = err.span(self.original_input);
+            DeError::Parse {
+                offset,
+                len,
+                message: err.message(),
+            }
+        })?;
 
         self.input = parsed.0;
         Ok(parsed.1)
     }
 
+    // run whichever parser the config asks for
+    fn parse<T>(
+        &mut self,
+        lenient: fn(Input<'de>) -> mon::Result<'de, T>,
+        strict: fn(Input<'de>) -> mon::Result<'de, T>,
+    ) -> Result<T> {
+        let parser = if self.config.strict { strict } else { lenient };
+        self.update(parser(self.input))
+    }
+
+    // attach the position in the input to an error
+    pub(super) fn diagnose<T>(&self, error: Error) -> Error {
+        let diagnostic = Diagnostic::new(
+            std::any::type_name::<T>(),
+            self.path.clone(),
+            self.len_read(),
+            error,
+            self.original_input,
+        );
+
+        Error::Diagnostic(Box::new(diagnostic))
+    }
+
     pub(super) fn len_read(&self) -> usize {
         use nom::Offset;
         self.original_input.offset(self.input)
     }
 
-    fn deserialize_seq_known_length<V>(&mut self, visitor: V) -> Result<V::Value>
+    // error if there is any input left over
+    pub(super) fn end(&self) -> Result<()> {
+        if self.input.is_empty() {
+            Ok(())
+        } else {
+            Err(DeError::TrailingData {
+                offset: self.len_read(),
+                rest: self.input.to_vec(),
+            })?
+        }
+    }
+
+    // checks that only apply once the whole value has been read
+    pub(super) fn finish(&self) -> Result<()> {
+        if self.config.exact {
+            self.end()?;
+        }
+
+        Ok(())
+    }
+
+    // fields is None for tuples, whose elements are only known by index
+    fn deserialize_seq_known_length<V>(
+        &mut self,
+        fields: Option<&'static [&'static str]>,
+        visitor: V,
+    ) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_seq(self)
+        visitor.visit_seq(KnownLenDeserializer::new(self, fields))
     }
 }
 
@@ -58,7 +127,7 @@ impl<'de, 'a> SDeserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        visitor.visit_bool(self.update(bool::parse(self.input))?)
+        visitor.visit_bool(self.parse(bool::parse, bool::parse_strict)?)
     }
 
     fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value>
@@ -156,7 +225,7 @@ impl<'de, 'a> SDeserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        visitor.visit_borrowed_str(self.update(String::parse(self.input))?)
+        visitor.visit_borrowed_str(self.parse(String::parse, String::parse_strict)?)
     }
 
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
@@ -186,7 +255,7 @@ impl<'de, 'a> SDeserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        let is_some = self.update(bool::parse(self.input))?;
+        let is_some = self.parse(bool::parse, bool::parse_strict)?;
         if is_some {
             visitor.visit_some(self)
         } else {
@@ -221,7 +290,7 @@ impl<'de, 'a> SDeserializer<'de> for &'a mut Deserializer<'de> {
         V: Visitor<'de>,
     {
         // visitor.visit_seq(self)
-        let len = self.update(VarInt::parse_as_usize(self.input))?;
+        let len = self.parse(VarInt::parse_as_usize, VarInt::parse_as_usize_strict)?;
         visitor.visit_seq(VecDeserializer::new(&mut self, len))
     }
 
@@ -230,7 +299,7 @@ impl<'de, 'a> SDeserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        self.deserialize_seq_known_length(visitor)
+        self.deserialize_seq_known_length(None, visitor)
     }
 
     fn deserialize_tuple_struct<V>(
@@ -242,14 +311,16 @@ impl<'de, 'a> SDeserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        self.deserialize_seq_known_length(visitor)
+        self.deserialize_seq_known_length(None, visitor)
     }
 
-    fn deserialize_map<V>(self, _visitor: V) -> Result<V::Value>
+    // map with a VarInt count of key/value pairs
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        Err(DeError::InvalidType("map"))?
+        let len = self.parse(VarInt::parse_as_usize, VarInt::parse_as_usize_strict)?;
+        visitor.visit_map(MapDeserializer::new(self, len))
     }
 
     // structs are stored the same as their contents because we dont store keys
@@ -263,22 +334,22 @@ impl<'de, 'a> SDeserializer<'de> for &'a mut Deserializer<'de> {
         V: Visitor<'de>,
     {
         if name == varnum::VARINT_NAME && fields == [varnum::VARINT_FIELD] {
-            let value = self.update(VarInt::parse(self.input))?;
+            let value = self.parse(VarInt::parse, VarInt::parse_strict)?;
             return visitor.visit_map(varnum::VarIntDeserializer::new(value));
         }
 
         if name == varnum::VARLONG_NAME && fields == [varnum::VARLONG_FIELD] {
-            let value = self.update(VarLong::parse(self.input))?;
+            let value = self.parse(VarLong::parse, VarLong::parse_strict)?;
             return visitor.visit_map(varnum::VarLongDeserializer::new(value));
         }
 
-        self.deserialize_seq_known_length(visitor)
+        self.deserialize_seq_known_length(Some(fields), visitor)
     }
 
     fn deserialize_enum<V>(
         self,
         _name: &'static str,
-        _variants: &'static [&'static str],
+        variants: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value>
     where
@@ -289,7 +360,7 @@ impl<'de, 'a> SDeserializer<'de> for &'a mut Deserializer<'de> {
         //     std::any::type_name::<V>(),
         //     std::any::type_name::<V::Value>()
         // );
-        visitor.visit_enum(self)
+        visitor.visit_enum(EnumDeserializer::new(self, variants))
     }
 
     fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
@@ -317,8 +388,28 @@ impl<'de, 'a> SDeserializer<'de> for &'a mut Deserializer<'de> {
     }
 }
 
-impl<'de> SeqAccess<'de> for Deserializer<'de> {
-    type Error = <&'de mut Self as SDeserializer<'de>>::Error;
+// tuples and structs, which have no length prefix and are read until the input runs out
+struct KnownLenDeserializer<'a, 'de: 'a> {
+    deserializer: &'a mut Deserializer<'de>,
+    fields: Option<&'static [&'static str]>,
+    index: usize,
+}
+
+impl<'a, 'de> KnownLenDeserializer<'a, 'de> {
+    fn new(
+        deserializer: &'a mut Deserializer<'de>,
+        fields: Option<&'static [&'static str]>,
+    ) -> Self {
+        Self {
+            deserializer,
+            fields,
+            index: 0,
+        }
+    }
+}
+
+impl<'de, 'a> SeqAccess<'de> for KnownLenDeserializer<'a, 'de> {
+    type Error = <&'de mut Deserializer<'de> as SDeserializer<'de>>::Error;
 
     fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
     where
@@ -330,17 +421,41 @@ impl<'de> SeqAccess<'de> for Deserializer<'de> {
         //     std::any::type_name::<T::Value>()
         // );
 
-        if self.input.len() == 0 {
+        if self.deserializer.input.is_empty() {
             return Ok(None);
         }
 
-        seed.deserialize(self).map(Some)
+        let segment = match self.fields.and_then(|fields| fields.get(self.index)) {
+            Some(field) => PathSegment::Field(field),
+            None => PathSegment::Index(self.index),
+        };
+        self.index += 1;
+
+        self.deserializer.path.push(segment);
+        let value = seed.deserialize(&mut *self.deserializer)?;
+        self.deserializer.path.pop();
+
+        Ok(Some(value))
     }
 }
 
-impl<'de, 'a> EnumAccess<'de> for &'a mut Deserializer<'de> {
-    type Error = <Self as SDeserializer<'de>>::Error;
-    type Variant = Self;
+struct EnumDeserializer<'a, 'de: 'a> {
+    deserializer: &'a mut Deserializer<'de>,
+    variants: &'static [&'static str],
+}
+
+impl<'a, 'de> EnumDeserializer<'a, 'de> {
+    fn new(deserializer: &'a mut Deserializer<'de>, variants: &'static [&'static str]) -> Self {
+        Self {
+            deserializer,
+            variants,
+        }
+    }
+}
+
+impl<'de, 'a> EnumAccess<'de> for EnumDeserializer<'a, 'de> {
+    type Error = <&'a mut Deserializer<'de> as SDeserializer<'de>>::Error;
+    type Variant = &'a mut Deserializer<'de>;
 
     fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
     where
@@ -351,7 +466,24 @@ impl<'de, 'a> EnumAccess<'de> for &'a mut Deserializer<'de> {
         //     std::any::type_name::<V>(),
         //     std::any::type_name::<V::Value>()
         // );
-        seed.deserialize(&mut *self).map(|val| (val, self))
+
+        // the variant is a single byte, see deserialize_identifier
+        let variant = self
+            .deserializer
+            .input
+            .first()
+            .and_then(|&index| self.variants.get(index as usize));
+
+        let value = seed.deserialize(&mut *self.deserializer)?;
+
+        // popped once the contents of the variant are deserialized, in VariantAccess
+        let segment = match variant {
+            Some(variant) => PathSegment::Variant(variant),
+            None => PathSegment::Variant("?"),
+        };
+        self.deserializer.path.push(segment);
+
+        Ok((value, self.deserializer))
     }
 }
 
@@ -371,6 +503,7 @@ impl<'de, 'a> VariantAccess<'de> for &'a mut Deserializer<'de> {
     // Handles Foo::One
     fn unit_variant(self) -> Result<()> {
         // dbg!("delta");
+        self.path.pop();
         Ok(())
     }
 
@@ -384,7 +517,9 @@ impl<'de, 'a> VariantAccess<'de> for &'a mut Deserializer<'de> {
         //     std::any::type_name::<T>(),
         //     std::any::type_name::<T::Value>()
         // );
-        seed.deserialize(self)
+        let value = seed.deserialize(&mut *self)?;
+        self.path.pop();
+        Ok(value)
     }
 
     // Handles Foo::{ Two, Four }
@@ -397,11 +532,13 @@ impl<'de, 'a> VariantAccess<'de> for &'a mut Deserializer<'de> {
         //     std::any::type_name::<V>(),
         //     std::any::type_name::<V::Value>()
         // );
-        self.deserialize_seq_known_length(visitor)
+        let value = self.deserialize_seq_known_length(None, visitor)?;
+        self.path.pop();
+        Ok(value)
     }
 
     // Handles Foo::Five
-    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
@@ -410,18 +547,25 @@ impl<'de, 'a> VariantAccess<'de> for &'a mut Deserializer<'de> {
         //     std::any::type_name::<V>(),
         //     std::any::type_name::<V::Value>()
         // );
-        self.deserialize_seq_known_length(visitor)
+        let value = self.deserialize_seq_known_length(Some(fields), visitor)?;
+        self.path.pop();
+        Ok(value)
     }
 }
 
 struct VecDeserializer<'a, 'de: 'a> {
     deserializer: &'a mut Deserializer<'de>,
     len: usize,
+    index: usize,
 }
 
 impl<'a, 'de> VecDeserializer<'a, 'de> {
     fn new(deserializer: &'a mut Deserializer<'de>, len: usize) -> Self {
-        Self { deserializer, len }
+        Self {
+            deserializer,
+            len,
+            index: 0,
+        }
     }
 }
 
@@ -436,10 +580,69 @@ impl<'de, 'a> SeqAccess<'de> for VecDeserializer<'a, 'de> {
             Ok(None)
         } else {
             self.len -= 1;
-            seed.deserialize(&mut *self.deserializer).map(Some)
+
+            self.deserializer.path.push(PathSegment::Index(self.index));
+            self.index += 1;
+            let value = seed.deserialize(&mut *self.deserializer)?;
+            self.deserializer.path.pop();
+
+            Ok(Some(value))
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+struct MapDeserializer<'a, 'de: 'a> {
+    deserializer: &'a mut Deserializer<'de>,
+    len: usize,
+    index: usize,
+}
+
+impl<'a, 'de> MapDeserializer<'a, 'de> {
+    fn new(deserializer: &'a mut Deserializer<'de>, len: usize) -> Self {
+        Self {
+            deserializer,
+            len,
+            index: 0,
+        }
+    }
+}
+
+impl<'de, 'a> MapAccess<'de> for MapDeserializer<'a, 'de> {
+    type Error = <&'de mut Deserializer<'de> as SDeserializer<'de>>::Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        if self.len == 0 {
+            Ok(None)
+        } else {
+            self.len -= 1;
+
+            self.deserializer.path.push(PathSegment::Index(self.index));
+            let key = seed.deserialize(&mut *self.deserializer)?;
+            self.deserializer.path.pop();
+
+            Ok(Some(key))
         }
     }
 
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        self.deserializer.path.push(PathSegment::Index(self.index));
+        self.index += 1;
+        let value = seed.deserialize(&mut *self.deserializer)?;
+        self.deserializer.path.pop();
+
+        Ok(value)
+    }
+
     fn size_hint(&self) -> Option<usize> {
         Some(self.len)
     }
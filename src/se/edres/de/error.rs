@@ -1,6 +1,6 @@
-use crate::se::error::Error;
+use crate::se::{error::Error, mon};
 use serde::de;
-use std::fmt::Display;
+use std::fmt::{self, Display};
 use thiserror::Error as ThisError;
 
 #[derive(ThisError, Debug)]
@@ -11,10 +11,120 @@ pub enum DeError {
     VarIntKey,
     #[error("VarInt field not found")]
     VarIntField,
+    #[error("{} bytes of trailing data after offset {offset}", rest.len())]
+    TrailingData { offset: usize, rest: Vec<u8> },
+    #[error("{message}")]
+    Parse {
+        offset: usize,
+        len: usize,
+        message: String,
+    },
     #[error("{0}")]
     Other(String),
 }
 
+// one step from a type to the value inside it that was being deserialized
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PathSegment {
+    Field(&'static str),
+    Index(usize),
+    Variant(&'static str),
+}
+
+impl Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Field(name) => write!(f, ".{}", name),
+            Self::Index(index) => write!(f, "[{}]", index),
+            Self::Variant(name) => write!(f, "::{}", name),
+        }
+    }
+}
+
+// an error from one of the deserialize functions, with the position in the input it happened at
+pub struct Diagnostic {
+    pub type_name: &'static str,
+    // the value that was being deserialized when the error happened, starting from type_name
+    pub path: Vec<PathSegment>,
+    pub offset: usize,
+    pub len: usize,
+    pub error: Error,
+    // empty if the input was not available, eg. from_reader()
+    input: Vec<u8>,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(
+        type_name: &'static str,
+        path: Vec<PathSegment>,
+        offset: usize,
+        error: Error,
+        input: &[u8],
+    ) -> Self {
+        // errors from the parsers know exactly which bytes were bad
+        let (offset, len) = match &error {
+            Error::SerdeDe(DeError::Parse { offset, len, .. }) => (*offset, *len),
+            Error::SerdeDe(DeError::TrailingData { offset, rest }) => (*offset, rest.len()),
+            _ => (offset, 0),
+        };
+
+        Self {
+            type_name,
+            path,
+            offset,
+            len,
+            error,
+            input: input.to_vec(),
+        }
+    }
+
+    pub fn input(&self) -> &[u8] {
+        &self.input
+    }
+
+    // type_name without the module path or generics, eg. "JoinGame"
+    pub fn short_type_name(&self) -> &'static str {
+        let name = self.type_name.split('<').next().unwrap_or(self.type_name);
+        name.rsplit("::").next().unwrap_or(name)
+    }
+
+    // eg. "Advancements.advancements[12].display.flags"
+    pub fn path_string(&self) -> String {
+        let mut output = self.short_type_name().to_string();
+        for segment in &self.path {
+            output += &segment.to_string();
+        }
+        output
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at offset {}: {}",
+            self.path_string(),
+            self.offset,
+            self.error
+        )
+    }
+}
+
+impl fmt::Debug for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "/!\\ {}", self)?;
+
+        if self.offset < self.input.len() {
+            // always highlight at least one byte, so the position is visible
+            mon::hexdump(f, &self.input, self.offset, self.len.max(1))?;
+        } else if !self.input.is_empty() {
+            writeln!(f, "    (at the end of {} bytes of input)", self.input.len())?;
+        }
+
+        Ok(())
+    }
+}
+
 impl de::Error for Error {
     fn custom<T: Display>(msg: T) -> Self {
         DeError::Other(msg.to_string()).into()
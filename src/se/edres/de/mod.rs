@@ -1,19 +1,64 @@
 mod de;
 pub mod error;
+mod read;
 mod types;
 mod varnum;
 
 use crate::se::error::Result;
 use crate::se::Input;
 use de::Deserializer;
-use serde::Deserialize;
+use read::ReadDeserializer;
+use serde::{de::DeserializeOwned, Deserialize};
+use std::io::BufRead;
+
+// how forgiving the deserializers are about malformed input
+// the default accepts anything the vanilla client or server would, and a bit more
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DeserializerConfig {
+    // reject bools other than 0 and 1, over-long VarInts and VarLongs (including length
+    // prefixes), and strings longer than the protocol maximum
+    pub strict: bool,
+    // it is an error if the input is not entirely consumed
+    pub exact: bool,
+}
+
+impl DeserializerConfig {
+    // everything turned on, for fuzzing and validating traffic
+    pub fn strict() -> Self {
+        Self {
+            strict: true,
+            exact: true,
+        }
+    }
+}
 
 pub fn from_bytes<'de, T>(input: Input<'de>) -> Result<T>
 where
     T: Deserialize<'de>,
 {
-    let mut deserializer = Deserializer::new(input);
-    let t = T::deserialize(&mut deserializer)?;
+    from_bytes_with_config(input, DeserializerConfig::default())
+}
+
+// same as from_bytes(), but it is an error if the input is not entirely consumed
+pub fn from_bytes_exact<'de, T>(input: Input<'de>) -> Result<T>
+where
+    T: Deserialize<'de>,
+{
+    let config = DeserializerConfig {
+        exact: true,
+        ..Default::default()
+    };
+    from_bytes_with_config(input, config)
+}
+
+pub fn from_bytes_with_config<'de, T>(input: Input<'de>, config: DeserializerConfig) -> Result<T>
+where
+    T: Deserialize<'de>,
+{
+    let mut deserializer = Deserializer::with_config(input, config);
+    let t = T::deserialize(&mut deserializer)
+        .and_then(|t| deserializer.finish().map(|_| t))
+        .map_err(|err| deserializer.diagnose::<T>(err))?;
     Ok(t)
 }
 
@@ -23,6 +68,28 @@ where
     T: Deserialize<'de>,
 {
     let mut deserializer = Deserializer::new(input);
-    let result = T::deserialize(&mut deserializer);
+    let result = T::deserialize(&mut deserializer).map_err(|err| deserializer.diagnose::<T>(err));
     (deserializer.input, result)
 }
+
+// reads the input incrementally instead of needing it all in one slice
+// prefer from_bytes when the input is already in memory, since it can borrow from it
+pub fn from_reader<R, T>(reader: R) -> Result<T>
+where
+    R: BufRead,
+    T: DeserializeOwned,
+{
+    from_reader_with_config(reader, DeserializerConfig::default())
+}
+
+pub fn from_reader_with_config<R, T>(reader: R, config: DeserializerConfig) -> Result<T>
+where
+    R: BufRead,
+    T: DeserializeOwned,
+{
+    let mut deserializer = ReadDeserializer::with_config(reader, config);
+    let t = T::deserialize(&mut deserializer)
+        .and_then(|t| deserializer.finish().map(|_| t))
+        .map_err(|err| deserializer.diagnose::<T>(err))?;
+    Ok(t)
+}
@@ -0,0 +1,576 @@
+use super::{
+    error::{DeError, Diagnostic, PathSegment},
+    varnum, DeserializerConfig,
+};
+use crate::se::{
+    error::{Error, Result},
+    mon::MAX_STRING_LEN,
+    VarInt, VarLong,
+};
+use crate::util;
+use serde::de::{
+    DeserializeSeed, Deserializer as SDeserializer, EnumAccess, MapAccess, SeqAccess,
+    VariantAccess, Visitor,
+};
+use std::io::{self, BufRead, Read};
+
+// Deserializer that pulls its input from a BufRead as it goes, instead of needing the whole
+// packet in one slice. Nothing can be borrowed from the input, so it only works for owned types.
+pub(super) struct ReadDeserializer<R> {
+    pub(super) reader: R,
+    pub(super) len_read: usize,
+    // see Deserializer::path
+    pub(super) path: Vec<PathSegment>,
+    config: DeserializerConfig,
+}
+
+impl<R: BufRead> ReadDeserializer<R> {
+    pub(super) fn with_config(reader: R, config: DeserializerConfig) -> Self {
+        Self {
+            reader,
+            len_read: 0,
+            path: Vec::new(),
+            config,
+        }
+    }
+
+    // see Deserializer::finish
+    pub(super) fn finish(&mut self) -> Result<()> {
+        if self.config.exact && !self.is_eof()? {
+            let offset = self.len_read;
+            let mut rest = Vec::new();
+            self.reader.read_to_end(&mut rest)?;
+            Err(DeError::TrailingData { offset, rest })?;
+        }
+
+        Ok(())
+    }
+
+    // the same errors the slice Deserializer produces from the strict parsers
+    fn reject<T>(&self, start: usize, message: String) -> Result<T> {
+        Err(DeError::Parse {
+            offset: start,
+            len: self.len_read - start,
+            message,
+        })?
+    }
+
+    // attach the position in the input to an error
+    pub(super) fn diagnose<T>(&self, error: Error) -> Error {
+        let diagnostic = Diagnostic::new(
+            std::any::type_name::<T>(),
+            self.path.clone(),
+            self.len_read,
+            error,
+            &[],
+        );
+
+        Error::Diagnostic(Box::new(diagnostic))
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        match self.reader.read_exact(buf) {
+            Ok(()) => {
+                self.len_read += buf.len();
+                Ok(())
+            }
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Err(Error::Eof),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn read_vec(&mut self, len: usize) -> Result<Vec<u8>> {
+        // dont trust len for the allocation, it could be garbage
+        let mut buf = Vec::with_capacity(len.min(self.reader.fill_buf()?.len()));
+        let amt = (&mut self.reader).take(len as u64).read_to_end(&mut buf)?;
+        self.len_read += amt;
+
+        if amt < len {
+            return Err(Error::Eof);
+        }
+
+        Ok(buf)
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        let mut buf = [0u8; 1];
+        self.read_exact(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    fn read_bool(&mut self) -> Result<bool> {
+        // see Parse<bool> for bool
+        let byte = self.read_u8()?;
+        if self.config.strict && byte > 0x01 {
+            return self.reject(self.len_read - 1, format!("0x{:02x} not valid bool", byte));
+        }
+
+        Ok(byte != 0x00)
+    }
+
+    fn read_varint(&mut self) -> Result<i32> {
+        let (result, amt) = util::count_reads(&mut self.reader, |reader| VarInt::_parse(reader));
+        self.len_read += amt;
+        let value = result?;
+
+        // see VarInt::parse_strict
+        if self.config.strict && VarInt(value).encoded_len() < amt {
+            let msg = format!("VarInt {} is over-long, encoded in {} bytes", value, amt);
+            return self.reject(self.len_read - amt, msg);
+        }
+
+        Ok(value)
+    }
+
+    fn read_varlong(&mut self) -> Result<i64> {
+        let (result, amt) = util::count_reads(&mut self.reader, |reader| VarLong::_parse(reader));
+        self.len_read += amt;
+        let value = result?;
+
+        if self.config.strict && VarLong(value).encoded_len() < amt {
+            let msg = format!("VarLong {} is over-long, encoded in {} bytes", value, amt);
+            return self.reject(self.len_read - amt, msg);
+        }
+
+        Ok(value)
+    }
+
+    fn read_len(&mut self) -> Result<usize> {
+        let len = self.read_varint()?;
+        Ok(std::convert::TryFrom::try_from(len)?)
+    }
+
+    fn is_eof(&mut self) -> Result<bool> {
+        Ok(self.reader.fill_buf()?.is_empty())
+    }
+
+    fn deserialize_seq_known_length<'de, V>(
+        &mut self,
+        fields: Option<&'static [&'static str]>,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_seq(KnownLenAccess {
+            deserializer: self,
+            fields,
+            index: 0,
+        })
+    }
+}
+
+macro_rules! impl_deserialize_num {
+    ($fn_name:ident, $visit_name:ident, $typ:ty) => {
+        fn $fn_name<V>(self, visitor: V) -> Result<V::Value>
+        where
+            V: Visitor<'de>,
+        {
+            let mut buf = [0u8; std::mem::size_of::<$typ>()];
+            self.read_exact(&mut buf)?;
+            visitor.$visit_name(<$typ>::from_be_bytes(buf))
+        }
+    };
+}
+
+impl<'de, R: BufRead> SDeserializer<'de> for &mut ReadDeserializer<R> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(DeError::InvalidType("any"))?
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_bool(self.read_bool()?)
+    }
+
+    impl_deserialize_num!(deserialize_i8, visit_i8, i8);
+    impl_deserialize_num!(deserialize_i16, visit_i16, i16);
+    impl_deserialize_num!(deserialize_i32, visit_i32, i32);
+    impl_deserialize_num!(deserialize_i64, visit_i64, i64);
+    impl_deserialize_num!(deserialize_i128, visit_i128, i128);
+
+    impl_deserialize_num!(deserialize_u8, visit_u8, u8);
+    impl_deserialize_num!(deserialize_u16, visit_u16, u16);
+    impl_deserialize_num!(deserialize_u32, visit_u32, u32);
+    impl_deserialize_num!(deserialize_u64, visit_u64, u64);
+    impl_deserialize_num!(deserialize_u128, visit_u128, u128);
+
+    impl_deserialize_num!(deserialize_f32, visit_f32, f32);
+    impl_deserialize_num!(deserialize_f64, visit_f64, f64);
+
+    fn deserialize_char<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(DeError::InvalidType("char"))?
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let start = self.len_read;
+        let len = self.read_len()?;
+
+        // see String::parse_strict
+        if self.config.strict && len > MAX_STRING_LEN * 3 {
+            let msg = format!(
+                "string of {} bytes is longer than the protocol maximum",
+                len
+            );
+            return self.reject(start, msg);
+        }
+
+        let data = self.read_vec(len)?;
+        let text = String::from_utf8(data).map_err(|err| DeError::Other(err.to_string()))?;
+
+        let units = text.encode_utf16().count();
+        if self.config.strict && units > MAX_STRING_LEN {
+            let msg = format!(
+                "string of {} characters is longer than the protocol maximum of {}",
+                units, MAX_STRING_LEN
+            );
+            return self.reject(start, msg);
+        }
+
+        visitor.visit_string(text)
+    }
+
+    // the rest of the input, see Deserializer::deserialize_bytes
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let mut data = Vec::new();
+        self.len_read += self.reader.read_to_end(&mut data)?;
+        visitor.visit_byte_buf(data)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if self.read_bool()? {
+            visitor.visit_some(self)
+        } else {
+            visitor.visit_none()
+        }
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let len = self.read_len()?;
+        visitor.visit_seq(CountedAccess::new(self, len))
+    }
+
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq_known_length(None, visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq_known_length(None, visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let len = self.read_len()?;
+        visitor.visit_map(CountedAccess::new(self, len))
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if name == varnum::VARINT_NAME && fields == [varnum::VARINT_FIELD] {
+            let value = self.read_varint()?;
+            return visitor.visit_map(varnum::VarIntDeserializer::new(value));
+        }
+
+        if name == varnum::VARLONG_NAME && fields == [varnum::VARLONG_FIELD] {
+            let value = self.read_varlong()?;
+            return visitor.visit_map(varnum::VarLongDeserializer::new(value));
+        }
+
+        self.deserialize_seq_known_length(Some(fields), visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_enum(EnumAccessor {
+            deserializer: self,
+            variants,
+        })
+    }
+
+    // see Deserializer::deserialize_identifier
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_u8(visitor)
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_any(visitor)
+    }
+
+    #[inline]
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+// tuples and structs, see KnownLenDeserializer
+struct KnownLenAccess<'a, R> {
+    deserializer: &'a mut ReadDeserializer<R>,
+    fields: Option<&'static [&'static str]>,
+    index: usize,
+}
+
+impl<'de, 'a, R: BufRead> SeqAccess<'de> for KnownLenAccess<'a, R> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if self.deserializer.is_eof()? {
+            return Ok(None);
+        }
+
+        let segment = match self.fields.and_then(|fields| fields.get(self.index)) {
+            Some(field) => PathSegment::Field(field),
+            None => PathSegment::Index(self.index),
+        };
+        self.index += 1;
+
+        self.deserializer.path.push(segment);
+        let value = seed.deserialize(&mut *self.deserializer)?;
+        self.deserializer.path.pop();
+
+        Ok(Some(value))
+    }
+}
+
+struct EnumAccessor<'a, R> {
+    deserializer: &'a mut ReadDeserializer<R>,
+    variants: &'static [&'static str],
+}
+
+impl<'de, 'a, R: BufRead> EnumAccess<'de> for EnumAccessor<'a, R> {
+    type Error = Error;
+    type Variant = &'a mut ReadDeserializer<R>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        // the variant is a single byte, see Deserializer::deserialize_identifier
+        let variants = self.variants;
+        let variant = self
+            .deserializer
+            .reader
+            .fill_buf()?
+            .first()
+            .and_then(|&index| variants.get(index as usize));
+
+        let value = seed.deserialize(&mut *self.deserializer)?;
+
+        // popped once the contents of the variant are deserialized, in VariantAccess
+        let segment = match variant {
+            Some(variant) => PathSegment::Variant(variant),
+            None => PathSegment::Variant("?"),
+        };
+        self.deserializer.path.push(segment);
+
+        Ok((value, self.deserializer))
+    }
+}
+
+impl<'de, R: BufRead> VariantAccess<'de> for &mut ReadDeserializer<R> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        self.path.pop();
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        let value = seed.deserialize(&mut *self)?;
+        self.path.pop();
+        Ok(value)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let value = self.deserialize_seq_known_length(None, visitor)?;
+        self.path.pop();
+        Ok(value)
+    }
+
+    fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let value = self.deserialize_seq_known_length(Some(fields), visitor)?;
+        self.path.pop();
+        Ok(value)
+    }
+}
+
+// Vecs and maps, which are prefixed with a VarInt count
+struct CountedAccess<'a, R> {
+    deserializer: &'a mut ReadDeserializer<R>,
+    len: usize,
+    index: usize,
+}
+
+impl<'a, R> CountedAccess<'a, R> {
+    fn new(deserializer: &'a mut ReadDeserializer<R>, len: usize) -> Self {
+        Self {
+            deserializer,
+            len,
+            index: 0,
+        }
+    }
+}
+
+impl<'de, 'a, R: BufRead> SeqAccess<'de> for CountedAccess<'a, R> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if self.len == 0 {
+            Ok(None)
+        } else {
+            self.len -= 1;
+
+            self.deserializer.path.push(PathSegment::Index(self.index));
+            self.index += 1;
+            let value = seed.deserialize(&mut *self.deserializer)?;
+            self.deserializer.path.pop();
+
+            Ok(Some(value))
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+impl<'de, 'a, R: BufRead> MapAccess<'de> for CountedAccess<'a, R> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        if self.len == 0 {
+            Ok(None)
+        } else {
+            self.len -= 1;
+
+            self.deserializer.path.push(PathSegment::Index(self.index));
+            let key = seed.deserialize(&mut *self.deserializer)?;
+            self.deserializer.path.pop();
+
+            Ok(Some(key))
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        self.deserializer.path.push(PathSegment::Index(self.index));
+        self.index += 1;
+        let value = seed.deserialize(&mut *self.deserializer)?;
+        self.deserializer.path.pop();
+
+        Ok(value)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
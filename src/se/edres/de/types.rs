@@ -1,4 +1,6 @@
-use crate::se::Position;
+use crate::se::{
+    Angle, BoundedStr, ChatStr, Identifier, Position, PositionDelta, PositionLayout, Velocity,
+};
 use serde::de;
 
 impl<'de> de::Deserialize<'de> for Position {
@@ -6,22 +8,61 @@ impl<'de> de::Deserialize<'de> for Position {
     where
         D: de::Deserializer<'de>,
     {
-        let data = u64::deserialize(deserializer)?;
-
-        let mut x = (data >> 38) as i32;
-        let mut y = (data & 0xFFF) as i16;
-        let mut z = (data << 26 >> 38) as i32;
-
-        if x >= 2i32.pow(25) {
-            x -= 2i32.pow(26);
-        }
-        if y >= 2i16.pow(11) {
-            y -= 2i16.pow(12)
+        // the coordinates, like Serialize writes for formats like json
+        #[derive(serde::Deserialize)]
+        #[serde(rename = "Position")]
+        struct Coordinates {
+            x: i32,
+            y: i32,
+            z: i32,
         }
-        if z >= 2i32.pow(25) {
-            z -= 2i32.pow(26)
+
+        if deserializer.is_human_readable() {
+            let Coordinates { x, y, z } = Coordinates::deserialize(deserializer)?;
+            return Position::new(x, y, z).map_err(de::Error::custom);
         }
 
-        Ok(Position { x, y, z })
+        let data = i64::deserialize(deserializer)?;
+        Ok(Position::decode(data, PositionLayout::XZY))
+    }
+}
+
+impl<'de, S, const N: usize> de::Deserialize<'de> for BoundedStr<S, N>
+where
+    S: de::Deserialize<'de> + AsRef<str>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let s = S::deserialize(deserializer)?;
+        BoundedStr::new(s).map_err(de::Error::custom)
+    }
+}
+
+impl<'de> de::Deserialize<'de> for Identifier {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let s = ChatStr::<String>::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
     }
 }
+
+macro_rules! impl_deserialize_newtype {
+    ($name:ident, $inner:ty) => {
+        impl<'de> de::Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: de::Deserializer<'de>,
+            {
+                <$inner>::deserialize(deserializer).map($name)
+            }
+        }
+    };
+}
+
+impl_deserialize_newtype!(Angle, u8);
+impl_deserialize_newtype!(Velocity, i16);
+impl_deserialize_newtype!(PositionDelta, i16);
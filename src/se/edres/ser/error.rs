@@ -9,6 +9,10 @@ pub enum SerError {
     InvalidType(&'static str),
     #[error("variant too large '{0}'")]
     LargeVariant(u32),
+    #[error("invalid map entry: {0}")]
+    MapEntry(&'static str),
+    #[error("sequence declared {expected} elements but serialized {actual}")]
+    LengthMismatch { expected: usize, actual: usize },
     #[error("{0}")]
     Other(String),
 }
@@ -1,17 +1,61 @@
 pub mod error;
 mod ser;
+mod size;
 mod types;
 use crate::se::error::Result;
+use std::io::{BufWriter, Write};
+
+pub use ser::Serializer;
 
 pub fn serialize<T>(data: T) -> Result<Vec<u8>>
 where
     T: serde::Serialize,
 {
-    let mut ser = ser::Serializer {
-        output: Vec::new(),
-        fake: std::marker::PhantomData,
-    };
+    let mut output = Vec::new();
+    serialize_into(&mut output, data)?;
+    Ok(output)
+}
+
+// appends to the end of the buffer, so a single Vec can be cleared and reused between packets
+// on an error, the buffer is truncated back to what it was before
+pub fn serialize_into<T>(output: &mut Vec<u8>, data: T) -> Result<()>
+where
+    T: serde::Serialize,
+{
+    let len = output.len();
+    // a Vec needs no buffering in front of it
+    let result = data.serialize(&mut Serializer::new(&mut *output));
+    if result.is_err() {
+        output.truncate(len);
+    }
+    result
+}
+
+// on an error, part of the data may already have been written, so whatever was written is invalid
+// (for example, a seq that yields a different number of elements than it said it would is only
+// caught at the end)
+// every number and every byte of a Vec<u8> is written on its own, so they are gathered up and
+// written together at the end, instead of each being a syscall on a file or socket
+pub fn serialize_to_writer<W, T>(writer: W, data: T) -> Result<()>
+where
+    W: Write,
+    T: serde::Serialize,
+{
+    let mut ser = Serializer::new(BufWriter::new(writer));
+    data.serialize(&mut ser)?;
+    // writes out what is buffered, but leaves flushing the writer itself to the caller
+    ser.into_inner()
+        .into_inner()
+        .map_err(|err| err.into_error())?;
+    Ok(())
+}
 
+// number of bytes serialize() would output, without allocating
+pub fn encoded_len<T>(data: T) -> Result<usize>
+where
+    T: serde::Serialize,
+{
+    let mut ser = size::SizeSerializer::new();
     data.serialize(&mut ser)?;
-    Ok(ser.output)
+    Ok(ser.len())
 }
@@ -5,43 +5,70 @@ use crate::se::{
 };
 use serde::{ser, Serialize, Serializer as _};
 use std::convert::{TryFrom, TryInto};
+use std::io::Write;
 
-impl Serialize for VarInt {
-    fn serialize<S>(&self, ser: S) -> std::result::Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
-        use serde::ser::SerializeTuple;
+macro_rules! impl_serialize_var_num {
+    ($name:ident, $unsigned:ty, $human_readable:ident) => {
+        impl Serialize for $name {
+            fn serialize<S>(&self, ser: S) -> std::result::Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                use serde::ser::SerializeTuple;
 
-        let mut val: u32 = self.0 as _;
-        let mut ser = ser.serialize_tuple(0)?; // len is not checked by us
+                // formats like json get the number itself
+                if ser.is_human_readable() {
+                    return ser.$human_readable(self.0);
+                }
 
-        loop {
-            let mut tmp = (val & 0b01111111) as u8;
-            val >>= 7;
+                let mut val = self.0 as $unsigned;
+                let mut ser = ser.serialize_tuple(0)?; // len is not checked by us
 
-            if val != 0 {
-                tmp |= 0b10000000;
-            }
+                loop {
+                    let mut tmp = (val & 0b01111111) as u8;
+                    val >>= 7;
+
+                    if val != 0 {
+                        tmp |= 0b10000000;
+                    }
+
+                    ser.serialize_element(&tmp)?;
 
-            ser.serialize_element(&tmp)?;
+                    if val == 0 {
+                        break;
+                    }
+                }
 
-            if val == 0 {
-                break;
+                ser.end()
             }
         }
-
-        ser.end()
-    }
+    };
 }
 
-pub struct Serializer<'a> {
-    // output: &'a mut Vec<u8>,
-    pub(super) output: Vec<u8>,
-    pub(super) fake: std::marker::PhantomData<&'a ()>,
+impl_serialize_var_num!(VarInt, u32, serialize_i32);
+impl_serialize_var_num!(VarLong, u64, serialize_i64);
+
+// writes the encoded data directly into any io::Write, one write_all for each field, so the writer
+// should be buffered (serialize_to_writer does that)
+// sequences and maps that know their length up front are streamed without buffering
+pub struct Serializer<W> {
+    output: W,
 }
 
-impl<'a> Serializer<'a> {
+impl<W: Write> Serializer<W> {
+    pub fn new(output: W) -> Self {
+        Self { output }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.output.write_all(data)?;
+        Ok(())
+    }
+
     fn serialize_variant_as_u8(&mut self, variant: u32) -> Result<()> {
         // the spec has most enums have a VarInt variant, but they're all 0 <= x < 128
         // so just serialize a u8 and call it a day
@@ -58,27 +85,25 @@ impl<'a> Serializer<'a> {
 macro_rules! impl_serialize_num {
     ($fn_name:ident, $typ:ty) => {
         fn $fn_name(self, v: $typ) -> Result<()> {
-            self.output.extend_from_slice(&v.to_be_bytes());
-            Ok(())
+            self.write(&v.to_be_bytes())
         }
     };
 }
 
-impl<'a, 'b> ser::Serializer for &'b mut Serializer<'a> {
+impl<'b, W: Write> ser::Serializer for &'b mut Serializer<W> {
     type Ok = ();
     type Error = Error;
 
-    type SerializeSeq = VecSerializer<'a, 'b>;
+    type SerializeSeq = LenPrefixed<'b, W>;
     type SerializeTuple = Self;
     type SerializeTupleStruct = Self;
     type SerializeTupleVariant = Self;
-    type SerializeMap = Self;
+    type SerializeMap = LenPrefixed<'b, W>;
     type SerializeStruct = Self;
     type SerializeStructVariant = Self;
 
     fn serialize_bool(self, v: bool) -> Result<()> {
-        self.output.push(if v { 1 } else { 0 });
-        Ok(())
+        self.write(&[if v { 1 } else { 0 }])
     }
 
     impl_serialize_num!(serialize_i8, i8);
@@ -102,13 +127,11 @@ impl<'a, 'b> ser::Serializer for &'b mut Serializer<'a> {
 
     fn serialize_str(self, v: &str) -> Result<()> {
         VarInt::from_usize(v.len())?.serialize(&mut *self)?;
-        self.output.extend_from_slice(v.as_bytes());
-        Ok(())
+        self.write(v.as_bytes())
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<()> {
-        self.output.extend_from_slice(v);
-        Ok(())
+        self.write(v)
     }
 
     fn serialize_none(self) -> Result<()> {
@@ -163,7 +186,7 @@ impl<'a, 'b> ser::Serializer for &'b mut Serializer<'a> {
     }
 
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
-        Ok(VecSerializer::new(self, len))
+        LenPrefixed::new(self, len)
     }
 
     fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
@@ -189,8 +212,8 @@ impl<'a, 'b> ser::Serializer for &'b mut Serializer<'a> {
         Ok(self)
     }
 
-    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
-        panic!("no maps")
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
+        LenPrefixed::new(self, len)
     }
 
     fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
@@ -214,7 +237,7 @@ impl<'a, 'b> ser::Serializer for &'b mut Serializer<'a> {
     }
 }
 
-impl<'a, 'b> ser::SerializeTuple for &'b mut Serializer<'a> {
+impl<W: Write> ser::SerializeTuple for &mut Serializer<W> {
     type Ok = ();
     type Error = Error;
 
@@ -230,7 +253,7 @@ impl<'a, 'b> ser::SerializeTuple for &'b mut Serializer<'a> {
     }
 }
 
-impl<'a, 'b> ser::SerializeTupleStruct for &'b mut Serializer<'a> {
+impl<W: Write> ser::SerializeTupleStruct for &mut Serializer<W> {
     type Ok = ();
     type Error = Error;
 
@@ -246,7 +269,7 @@ impl<'a, 'b> ser::SerializeTupleStruct for &'b mut Serializer<'a> {
     }
 }
 
-impl<'a, 'b> ser::SerializeTupleVariant for &'b mut Serializer<'a> {
+impl<W: Write> ser::SerializeTupleVariant for &mut Serializer<W> {
     type Ok = ();
     type Error = Error;
 
@@ -262,30 +285,23 @@ impl<'a, 'b> ser::SerializeTupleVariant for &'b mut Serializer<'a> {
     }
 }
 
-impl<'a, 'b> ser::SerializeMap for &'b mut Serializer<'a> {
+impl<W: Write> ser::SerializeStruct for &mut Serializer<W> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_key<T>(&mut self, _key: &T) -> Result<()>
-    where
-        T: ?Sized + Serialize,
-    {
-        panic!()
-    }
-
-    fn serialize_value<T>(&mut self, _value: &T) -> Result<()>
+    fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<()>
     where
         T: ?Sized + Serialize,
     {
-        panic!()
+        value.serialize(&mut **self)
     }
 
     fn end(self) -> Result<()> {
-        panic!()
+        Ok(())
     }
 }
 
-impl<'a, 'b> ser::SerializeStruct for &'b mut Serializer<'a> {
+impl<W: Write> ser::SerializeStructVariant for &mut Serializer<W> {
     type Ok = ();
     type Error = Error;
 
@@ -301,61 +317,114 @@ impl<'a, 'b> ser::SerializeStruct for &'b mut Serializer<'a> {
     }
 }
 
-impl<'a, 'b> ser::SerializeStructVariant for &'b mut Serializer<'a> {
-    type Ok = ();
-    type Error = Error;
+// Vecs and maps are stored as a VarInt count followed by the elements (or keys and values)
+// If serde tells us the length up front, the count is written immediately and the elements are
+// streamed straight into the output. Otherwise, the elements are buffered until the count is known.
+// When streaming, a wrong length is only found at the end, after the count and elements have been
+// written, so the output is invalid after a LengthMismatch.
+pub struct LenPrefixed<'b, W> {
+    parent: &'b mut Serializer<W>,
+    expected: Option<usize>,
+    count: usize,
+    buffer: Option<Serializer<Vec<u8>>>,
+    has_key: bool,
+}
 
-    fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<()>
+impl<'b, W: Write> LenPrefixed<'b, W> {
+    fn new(parent: &'b mut Serializer<W>, len: Option<usize>) -> Result<Self> {
+        let buffer = match len {
+            Some(len) => {
+                VarInt::from_usize(len)?.serialize(&mut *parent)?;
+                None
+            }
+            None => Some(Serializer::new(Vec::new())),
+        };
+
+        Ok(Self {
+            parent,
+            expected: len,
+            count: 0,
+            buffer,
+            has_key: false,
+        })
+    }
+
+    fn serialize_item<T>(&mut self, value: &T) -> Result<()>
     where
         T: ?Sized + Serialize,
     {
-        value.serialize(&mut **self)
+        match &mut self.buffer {
+            Some(buffer) => value.serialize(buffer),
+            None => value.serialize(&mut *self.parent),
+        }
     }
 
-    fn end(self) -> Result<()> {
-        Ok(())
+    fn finish(self) -> Result<()> {
+        match self.buffer {
+            Some(buffer) => {
+                VarInt::from_usize(self.count)?.serialize(&mut *self.parent)?;
+                self.parent.write(&buffer.output)
+            }
+            None if Some(self.count) != self.expected => Err(SerError::LengthMismatch {
+                expected: self.expected.unwrap_or_default(),
+                actual: self.count,
+            })?,
+            None => Ok(()),
+        }
     }
 }
 
-pub struct VecSerializer<'a, 'b> {
-    parent: &'b mut Serializer<'a>,
-    len: i32,
-    serializer: Serializer<'a>,
-}
+impl<'b, W: Write> ser::SerializeSeq for LenPrefixed<'b, W> {
+    type Ok = ();
+    type Error = Error;
 
-impl<'a, 'b> VecSerializer<'a, 'b> {
-    fn new(parent: &'b mut Serializer<'a>, _len: Option<usize>) -> Self {
-        let output = Vec::new();
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + serde::Serialize,
+    {
+        self.count += 1;
+        self.serialize_item(value)
+    }
 
-        Self {
-            parent,
-            len: 0,
-            serializer: Serializer {
-                output: output,
-                fake: std::marker::PhantomData
-                // output: &mut parent.output,
-                // output: todo!(),
-            },
-        }
+    fn end(self) -> Result<()> {
+        self.finish()
     }
 }
 
-impl<'a, 'b> ser::SerializeSeq for VecSerializer<'a, 'b> {
+impl<'b, W: Write> ser::SerializeMap for LenPrefixed<'b, W> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
     where
-        T: ?Sized + serde::Serialize,
+        T: ?Sized + Serialize,
     {
-        self.len += 1;
-        value.serialize(&mut self.serializer)
+        if self.has_key {
+            Err(SerError::MapEntry("key serialized twice without a value"))?;
+        }
+
+        self.has_key = true;
+        self.serialize_item(key)
     }
 
-    fn end(mut self) -> Result<()> {
-        VarInt(self.len).serialize(&mut *self.parent)?;
-        self.parent.output.append(&mut self.serializer.output);
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        if !self.has_key {
+            Err(SerError::MapEntry("value serialized without a key"))?;
+        }
 
-        Ok(())
+        self.has_key = false;
+        self.count += 1;
+        self.serialize_item(value)
+    }
+
+    fn end(self) -> Result<()> {
+        if self.has_key {
+            Err(SerError::MapEntry("key serialized without a value"))?;
+        }
+
+        self.finish()
     }
 }
@@ -0,0 +1,325 @@
+use super::error::SerError;
+use crate::se::{
+    error::{Error, Result},
+    VarInt,
+};
+use serde::{ser, Serialize};
+use std::mem::size_of;
+
+// walks the data like Serializer would, but only adds up the number of bytes it would write
+pub struct SizeSerializer {
+    len: usize,
+}
+
+impl SizeSerializer {
+    pub fn new() -> Self {
+        Self { len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    fn add(&mut self, len: usize) -> Result<()> {
+        self.len += len;
+        Ok(())
+    }
+
+    fn add_variant(&mut self, variant: u32) -> Result<()> {
+        // see Serializer::serialize_variant_as_u8
+        if variant >= 128 {
+            Err(SerError::LargeVariant(variant))?;
+        }
+
+        self.add(1)
+    }
+}
+
+macro_rules! impl_size_num {
+    ($fn_name:ident, $typ:ty) => {
+        fn $fn_name(self, _v: $typ) -> Result<()> {
+            self.add(size_of::<$typ>())
+        }
+    };
+}
+
+impl<'b> ser::Serializer for &'b mut SizeSerializer {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = SizeLenPrefixed<'b>;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = SizeLenPrefixed<'b>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    impl_size_num!(serialize_bool, bool);
+
+    impl_size_num!(serialize_i8, i8);
+    impl_size_num!(serialize_i16, i16);
+    impl_size_num!(serialize_i32, i32);
+    impl_size_num!(serialize_i64, i64);
+    impl_size_num!(serialize_i128, i128);
+
+    impl_size_num!(serialize_u8, u8);
+    impl_size_num!(serialize_u16, u16);
+    impl_size_num!(serialize_u32, u32);
+    impl_size_num!(serialize_u64, u64);
+    impl_size_num!(serialize_u128, u128);
+
+    impl_size_num!(serialize_f32, f32);
+    impl_size_num!(serialize_f64, f64);
+
+    fn serialize_char(self, _v: char) -> Result<()> {
+        Err(SerError::InvalidType("char"))?
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.add(VarInt::from_usize(v.len())?.encoded_len() + v.len())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        self.add(v.len())
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        self.serialize_bool(false)
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.serialize_bool(true)?;
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<()> {
+        self.add_variant(variant_index)
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.add_variant(variant_index)?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Ok(SizeLenPrefixed::new(self))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        self.add_variant(variant_index)?;
+        Ok(self)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Ok(SizeLenPrefixed::new(self))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        self.add_variant(variant_index)?;
+        Ok(self)
+    }
+
+    #[inline]
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+impl ser::SerializeTuple for &mut SizeSerializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for &mut SizeSerializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for &mut SizeSerializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for &mut SizeSerializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for &mut SizeSerializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+// the VarInt count prefix is only added once all elements have been counted
+pub struct SizeLenPrefixed<'b> {
+    parent: &'b mut SizeSerializer,
+    count: usize,
+}
+
+impl<'b> SizeLenPrefixed<'b> {
+    fn new(parent: &'b mut SizeSerializer) -> Self {
+        Self { parent, count: 0 }
+    }
+
+    fn finish(self) -> Result<()> {
+        self.parent
+            .add(VarInt::from_usize(self.count)?.encoded_len())
+    }
+}
+
+impl<'b> ser::SerializeSeq for SizeLenPrefixed<'b> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.count += 1;
+        value.serialize(&mut *self.parent)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<'b> ser::SerializeMap for SizeLenPrefixed<'b> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        key.serialize(&mut *self.parent)
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.count += 1;
+        value.serialize(&mut *self.parent)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
@@ -1,4 +1,4 @@
-use crate::se::Position;
+use crate::se::{Angle, BoundedStr, Identifier, Position, PositionDelta, PositionLayout, Velocity};
 use serde::ser;
 
 impl ser::Serialize for Position {
@@ -6,11 +6,56 @@ impl ser::Serialize for Position {
     where
         S: ser::Serializer,
     {
-        let x = (self.x as i64) & 0x3FFFFFF;
-        let y = (self.y as i64) & 0xFFF;
-        let z = (self.z as i64) & 0x3FFFFFF;
+        // formats like json get the coordinates, instead of them packed into an i64
+        if serializer.is_human_readable() {
+            use ser::SerializeStruct;
 
-        let data = (x << 38) | (z << 12) | y;
+            let mut state = serializer.serialize_struct("Position", 3)?;
+            state.serialize_field("x", &self.x)?;
+            state.serialize_field("y", &self.y)?;
+            state.serialize_field("z", &self.z)?;
+            return state.end();
+        }
+
+        let data = self
+            .encode(PositionLayout::XZY)
+            .map_err(ser::Error::custom)?;
         serializer.serialize_i64(data)
     }
 }
+
+impl<S: AsRef<str>, const N: usize> ser::Serialize for BoundedStr<S, N> {
+    fn serialize<S2>(&self, serializer: S2) -> Result<S2::Ok, S2::Error>
+    where
+        S2: ser::Serializer,
+    {
+        self.check().map_err(ser::Error::custom)?;
+        serializer.serialize_str(self)
+    }
+}
+
+impl ser::Serialize for Identifier {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+macro_rules! impl_serialize_newtype {
+    ($name:ident) => {
+        impl ser::Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: ser::Serializer,
+            {
+                self.0.serialize(serializer)
+            }
+        }
+    };
+}
+
+impl_serialize_newtype!(Angle);
+impl_serialize_newtype!(Velocity);
+impl_serialize_newtype!(PositionDelta);
@@ -1,4 +1,4 @@
-use super::edres::de::error::DeError;
+use super::edres::de::error::{DeError, Diagnostic};
 use super::edres::ser::error::SerError;
 use super::{mon, Input};
 use std::io;
@@ -20,6 +20,9 @@ pub enum Error {
     #[error(transparent)]
     SerdeDe(#[from] DeError),
 
+    #[error("{0}")]
+    Diagnostic(Box<Diagnostic>),
+
     #[error(transparent)]
     SerdeSer(#[from] SerError),
 
@@ -36,8 +39,30 @@ pub enum Error {
     Other(String),
 }
 
+impl Error {
+    // the position and type info, if this error came from deserializing
+    pub fn diagnostic(&self) -> Option<&Diagnostic> {
+        match self {
+            Self::Diagnostic(diagnostic) => Some(diagnostic),
+            _ => None,
+        }
+    }
+}
+
+// without the original input, the offset is relative to the outermost slice the parser saw
 impl<'a> From<mon::Error<Input<'a>>> for Error {
     fn from(error: mon::Error<Input<'a>>) -> Self {
-        Self::Mon(format!("{:?}", error))
+        let original_input = match error.errors.last() {
+            Some((input, _)) => input,
+            None => return Self::Mon(error.message()),
+        };
+        let (offset, len) = error.span(original_input);
+
+        DeError::Parse {
+            offset,
+            len,
+            message: error.message(),
+        }
+        .into()
     }
 }
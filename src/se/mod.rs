@@ -3,12 +3,21 @@ mod error;
 mod mon;
 mod nbt;
 mod types;
+pub mod uuid;
 
 pub use self::nbt::DeNBTBlob;
-pub use edres::de::{from_bytes, from_bytes_debug};
-pub use edres::ser::serialize;
+pub use self::uuid::Uuid;
+pub use edres::de::{
+    error::{DeError, Diagnostic, PathSegment},
+    from_bytes, from_bytes_debug, from_bytes_exact, from_bytes_with_config, from_reader,
+    from_reader_with_config, DeserializerConfig,
+};
+pub use edres::ser::{encoded_len, serialize, serialize_into, serialize_to_writer, Serializer};
 pub use error::{Error, Result};
 pub use mon::Error as MonError;
-pub use types::{Position, VarInt, VarLong};
+pub use types::{
+    legacy_position, Angle, BoundedStr, ChatStr, ChunkPos, Direction, Hostname, Identifier,
+    Position, PositionDelta, PositionLayout, SectionPos, Username, VarInt, VarLong, Velocity,
+};
 
 pub type Input<'a> = &'a [u8];
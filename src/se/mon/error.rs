@@ -78,64 +78,92 @@ impl<I> ContextError<I> for Error<I> {
     }
 }
 
+impl<'a> Error<Input<'a>> {
+    // the innermost error's position relative to original_input, as (offset, len)
+    pub fn span(&self, original_input: Input<'a>) -> (usize, usize) {
+        use nom::Offset;
+        match self.errors.first() {
+            Some((input, _)) => (original_input.offset(input), input.len()),
+            None => (0, 0),
+        }
+    }
+
+    // one line summary, outermost context first
+    pub fn message(&self) -> String {
+        let messages: Vec<String> = self
+            .errors
+            .iter()
+            .rev()
+            .map(|(_, kind)| match kind {
+                ErrorKind::Context(ctx) => ctx.to_string(),
+                ErrorKind::Custom(ctx) => ctx.clone(),
+                ErrorKind::Nom(err) => format!("nom error {:?}", err),
+            })
+            .collect();
+
+        messages.join(": ")
+    }
+}
+
+const MARGIN_LEFT: usize = 4;
+
+// maximum amount of binary data we'll dump per line
+const MAXLEN: usize = 60;
+
+// given a big slice, an offset, and a length, attempt to show
+// some data before, some data after, and highlight which part
+// we're talking about with tildes.
+pub(crate) fn hexdump(f: &mut fmt::Formatter, s: &[u8], offset: usize, len: usize) -> fmt::Result {
+    let margin_str = " ".repeat(MARGIN_LEFT);
+
+    // decide which part of `s` we're going to show.
+    let (s, offset, len) = {
+        // see diagram further in article.
+        // TODO: review for off-by-one errors
+
+        let avail_after = s.len() - offset;
+        let after = std::cmp::min(avail_after, MAXLEN / 2);
+
+        let avail_before = offset;
+        let before = std::cmp::min(avail_before, MAXLEN / 2);
+
+        let new_start = offset - before;
+        let new_end = offset + after;
+        let new_offset = before;
+        let new_len = std::cmp::min(new_end - new_start, len);
+
+        (&s[new_start..new_end], new_offset, new_len)
+    };
+
+    write!(f, "{}", margin_str)?;
+    for b in s {
+        write!(f, "{:02X} ", b)?;
+    }
+    writeln!(f)?;
+
+    write!(f, "{}", margin_str)?;
+    for i in 0..s.len() {
+        // each byte takes three characters, ie "FF "
+        if i + 1 == offset + len {
+            // ..except the last one
+            write!(f, "~~")?;
+        } else if (offset..offset + len).contains(&i) {
+            write!(f, "~~~")?;
+        } else {
+            write!(f, "   ")?;
+        };
+    }
+    writeln!(f)?;
+
+    Ok(())
+}
+
 // stolen from https://fasterthanli.me/series/making-our-own-ping/part-9
 impl<'a> fmt::Debug for Error<Input<'a>> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "/!\\ parsing error\n")?;
+        writeln!(f, "/!\\ parsing error")?;
 
         let mut shown_input = None;
-        let margin_left = 4;
-        let margin_str = " ".repeat(margin_left);
-
-        // maximum amount of binary data we'll dump per line
-        let maxlen = 60;
-
-        // given a big slice, an offset, and a length, attempt to show
-        // some data before, some data after, and highlight which part
-        // we're talking about with tildes.
-        let print_slice =
-            |f: &mut fmt::Formatter, s: &[u8], offset: usize, len: usize| -> fmt::Result {
-                // decide which part of `s` we're going to show.
-                let (s, offset, len) = {
-                    // see diagram further in article.
-                    // TODO: review for off-by-one errors
-
-                    let avail_after = s.len() - offset;
-                    let after = std::cmp::min(avail_after, maxlen / 2);
-
-                    let avail_before = offset;
-                    let before = std::cmp::min(avail_before, maxlen / 2);
-
-                    let new_start = offset - before;
-                    let new_end = offset + after;
-                    let new_offset = before;
-                    let new_len = std::cmp::min(new_end - new_start, len);
-
-                    (&s[new_start..new_end], new_offset, new_len)
-                };
-
-                write!(f, "{}", margin_str)?;
-                for b in s {
-                    write!(f, "{:02X} ", b)?;
-                }
-                write!(f, "\n")?;
-
-                write!(f, "{}", margin_str)?;
-                for i in 0..s.len() {
-                    // each byte takes three characters, ie "FF "
-                    if i == offset + len - 1 {
-                        // ..except the last one
-                        write!(f, "~~")?;
-                    } else if (offset..offset + len).contains(&i) {
-                        write!(f, "~~~")?;
-                    } else {
-                        write!(f, "   ")?;
-                    };
-                }
-                write!(f, "\n")?;
-
-                Ok(())
-            };
 
         for (input, kind) in self.errors.iter().rev() {
             let prefix = match kind {
@@ -144,11 +172,11 @@ impl<'a> fmt::Debug for Error<Input<'a>> {
                 ErrorKind::Nom(err) => format!("nom error {:?}", err),
             };
 
-            write!(f, "{}\n", prefix)?;
+            writeln!(f, "{}", prefix)?;
             match shown_input {
                 None => {
                     shown_input.replace(input);
-                    print_slice(f, input, 0, input.len())?;
+                    hexdump(f, input, 0, input.len())?;
                 }
                 Some(parent_input) => {
                     // `nom::Offset` is a trait that lets us get the position
@@ -156,7 +184,7 @@ impl<'a> fmt::Debug for Error<Input<'a>> {
                     // our error reporting!
                     use nom::Offset;
                     let offset = parent_input.offset(input);
-                    print_slice(f, parent_input, offset, input.len())?;
+                    hexdump(f, parent_input, offset, input.len())?;
                 }
             };
         }
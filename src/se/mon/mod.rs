@@ -2,5 +2,6 @@
 mod error;
 mod primitives;
 
+pub(crate) use error::hexdump;
 pub use error::{Error, Result};
-pub use primitives::{Parse, ParseB};
+pub use primitives::{Parse, ParseB, MAX_STRING_LEN};
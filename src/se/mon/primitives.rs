@@ -6,12 +6,26 @@ use crate::se::{
 use nom::{bytes::complete::take, error::context, number::complete::be_u8};
 use std::convert::{TryFrom, TryInto};
 
+// maximum length of a protocol String, in UTF-16 code units
+// each one takes at most 3 bytes of UTF-8
+pub const MAX_STRING_LEN: usize = 32767;
+
 pub trait Parse<T> {
     fn parse(input: Input) -> MonResult<T>;
+
+    // like parse(), but also reject encodings that a vanilla implementation would never produce
+    fn parse_strict(input: Input) -> MonResult<T> {
+        Self::parse(input)
+    }
 }
 
 pub trait ParseB<T: ?Sized> {
     fn parse<'a>(input: Input<'a>) -> MonResult<&'a T>;
+
+    // see Parse::parse_strict()
+    fn parse_strict<'a>(input: Input<'a>) -> MonResult<'a, &'a T> {
+        Self::parse(input)
+    }
 }
 
 // shared impl for variable length numbers, VarInt and VarLong
@@ -46,6 +60,23 @@ macro_rules! var_num {
                 }
                 Ok((input, result))
             }
+
+            fn parse_strict(input: Input) -> MonResult<$type> {
+                let original_input = input;
+                let (input, result) = <$name as Parse<$type>>::parse(input)?;
+
+                // a trailing 0x00 byte adds nothing to the number, so it could have been left off
+                let len = original_input.len() - input.len();
+                if len > 1 && original_input[len - 1] == 0x00 {
+                    let msg = format!(
+                        concat!(stringify!($name), " {} is over-long, encoded in {} bytes"),
+                        result, len
+                    );
+                    return MonError::custom_slice(original_input, input, msg);
+                }
+
+                Ok((input, result))
+            }
         }
 
         impl $name {
@@ -54,8 +85,19 @@ macro_rules! var_num {
             }
 
             pub fn parse_as_usize(input: Input) -> MonResult<usize> {
+                Self::parse_as_usize_with(input, <$name as Parse<$type>>::parse)
+            }
+
+            pub fn parse_as_usize_strict(input: Input) -> MonResult<usize> {
+                Self::parse_as_usize_with(input, <$name as Parse<$type>>::parse_strict)
+            }
+
+            fn parse_as_usize_with(
+                input: Input,
+                parse: fn(Input) -> MonResult<$type>,
+            ) -> MonResult<usize> {
                 let original_input = input;
-                let (input, num) = <$name as Parse<$type>>::parse(input)?;
+                let (input, num) = parse(input)?;
 
                 let num = handle!(usize::try_from(num), original_input, input, |_| format!(
                     concat!(stringify!($type), " {} cannot fit in usize"),
@@ -135,6 +177,33 @@ impl ParseB<str> for String {
         // })(input)?;
         Ok((input, text))
     }
+
+    fn parse_strict<'a>(input: Input<'a>) -> MonResult<'a, &'a str> {
+        let original_input = input;
+        let (input, len) = context("string length", VarInt::parse_as_usize_strict)(input)?;
+
+        // check the byte length before reading, so a huge length fails here instead of at eof
+        if len > MAX_STRING_LEN * 3 {
+            let msg = format!(
+                "string of {} bytes is longer than the protocol maximum",
+                len
+            );
+            return MonError::custom_slice(original_input, input, msg);
+        }
+
+        let (input, text) = <String as ParseB<str>>::parse(original_input)?;
+
+        let units = text.encode_utf16().count();
+        if units > MAX_STRING_LEN {
+            let msg = format!(
+                "string of {} characters is longer than the protocol maximum of {}",
+                units, MAX_STRING_LEN
+            );
+            return MonError::custom_slice(original_input, input, msg);
+        }
+
+        Ok((input, text))
+    }
 }
 
 impl Parse<bool> for bool {
@@ -157,4 +226,20 @@ impl Parse<bool> for bool {
 
         Ok((input, result))
     }
+
+    // unless we're being strict, in which case it is not fine
+    fn parse_strict(input: Input) -> MonResult<bool> {
+        let original_input = input;
+        let (input, byte) = context("bool", take(1u8))(input)?;
+
+        match byte[0] {
+            0x00 => Ok((input, false)),
+            0x01 => Ok((input, true)),
+            invalid => MonError::custom_slice(
+                original_input,
+                input,
+                format!("0x{:02x} not valid bool", invalid),
+            ),
+        }
+    }
 }
@@ -1,8 +1,23 @@
+use crate::se::error::{Error, Result};
+
 macro_rules! var_num {
-    ($name:ident, $type:ty) => {
+    ($name:ident, $type:ty, $unsigned:ty) => {
         #[derive(Copy, Clone, PartialOrd, PartialEq, Ord, Eq)]
         pub struct $name(pub $type);
 
+        impl $name {
+            // number of bytes this number takes up when encoded
+            pub fn encoded_len(self) -> usize {
+                let mut val = self.0 as $unsigned;
+                let mut len = 1;
+                while val >= 0b10000000 {
+                    val >>= 7;
+                    len += 1;
+                }
+                len
+            }
+        }
+
         impl std::fmt::Debug for $name {
             #[inline(always)]
             fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -19,12 +34,452 @@ macro_rules! var_num {
     };
 }
 
-var_num!(VarInt, i32);
-var_num!(VarLong, i64);
+var_num!(VarInt, i32, u32);
+var_num!(VarLong, i64, u64);
 
-#[derive(Clone, Debug, PartialOrd, PartialEq, Ord, Eq)]
+#[derive(Copy, Clone, Debug, Default, PartialOrd, PartialEq, Ord, Eq, Hash)]
 pub struct Position {
     pub x: i32, // 26 bit
     pub y: i16, // 12 bit
     pub z: i32, // 26 bit
 }
+
+// How the three coordinates are packed into the 64 bits of a Position, from the top bit down
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum PositionLayout {
+    // 1.14 and later
+    #[default]
+    XZY,
+    // before 1.14
+    XYZ,
+}
+
+impl PositionLayout {
+    // protocol version of 18w43a, the first 1.14 snapshot
+    const XZY_PROTOCOL_VERSION: i32 = 441;
+
+    pub fn for_protocol_version(version: i32) -> Self {
+        if version >= Self::XZY_PROTOCOL_VERSION {
+            Self::XZY
+        } else {
+            Self::XYZ
+        }
+    }
+}
+
+impl Position {
+    pub const MIN_XZ: i32 = -(1 << 25);
+    pub const MAX_XZ: i32 = (1 << 25) - 1;
+    pub const MIN_Y: i16 = -(1 << 11);
+    pub const MAX_Y: i16 = (1 << 11) - 1;
+
+    pub fn new(x: i32, y: i32, z: i32) -> Result<Self> {
+        let in_range = |v: i32, min: i32, max: i32| min <= v && v <= max;
+
+        if !in_range(x, Self::MIN_XZ, Self::MAX_XZ)
+            || !in_range(y, Self::MIN_Y.into(), Self::MAX_Y.into())
+            || !in_range(z, Self::MIN_XZ, Self::MAX_XZ)
+        {
+            return Err(Error::Other(format!(
+                "position ({}, {}, {}) out of range",
+                x, y, z
+            )));
+        }
+
+        Ok(Self { x, y: y as i16, z })
+    }
+
+    // the fields are pub, so they could have been set to anything
+    pub fn check(&self) -> Result<()> {
+        Self::new(self.x, self.y.into(), self.z).map(|_| ())
+    }
+
+    pub fn encode(&self, layout: PositionLayout) -> Result<i64> {
+        self.check()?;
+
+        let x = (self.x as i64) & 0x3FFFFFF;
+        let y = (self.y as i64) & 0xFFF;
+        let z = (self.z as i64) & 0x3FFFFFF;
+
+        Ok(match layout {
+            PositionLayout::XZY => (x << 38) | (z << 12) | y,
+            PositionLayout::XYZ => (x << 38) | (y << 26) | z,
+        })
+    }
+
+    // every i64 is a valid position, the shifts sign extend each coordinate
+    pub fn decode(data: i64, layout: PositionLayout) -> Self {
+        match layout {
+            PositionLayout::XZY => Self {
+                x: (data >> 38) as i32,
+                y: (data << 52 >> 52) as i16,
+                z: (data << 26 >> 38) as i32,
+            },
+            PositionLayout::XYZ => Self {
+                x: (data >> 38) as i32,
+                y: (data << 26 >> 52) as i16,
+                z: (data << 38 >> 38) as i32,
+            },
+        }
+    }
+
+    pub fn offset(&self, dx: i32, dy: i32, dz: i32) -> Result<Self> {
+        Self::new(
+            self.x.saturating_add(dx),
+            i32::from(self.y).saturating_add(dy),
+            self.z.saturating_add(dz),
+        )
+    }
+
+    // the block touching this one on the given side
+    pub fn step(&self, direction: Direction) -> Result<Self> {
+        let (dx, dy, dz) = direction.offset();
+        self.offset(dx, dy, dz)
+    }
+
+    // in the same order as Direction::ALL, None for the ones outside the world
+    pub fn neighbours(&self) -> [Option<Self>; 6] {
+        let mut result = [None; 6];
+        for (neighbour, &direction) in result.iter_mut().zip(Direction::ALL.iter()) {
+            *neighbour = self.step(direction).ok();
+        }
+        result
+    }
+
+    pub fn chunk(&self) -> ChunkPos {
+        ChunkPos {
+            x: self.x >> 4,
+            z: self.z >> 4,
+        }
+    }
+
+    pub fn section(&self) -> SectionPos {
+        SectionPos {
+            x: self.x >> 4,
+            y: (self.y >> 4).into(),
+            z: self.z >> 4,
+        }
+    }
+
+    // coordinates within the section, each 0 to 15
+    pub fn in_section(&self) -> (u8, u8, u8) {
+        (
+            (self.x & 15) as u8,
+            (self.y & 15) as u8,
+            (self.z & 15) as u8,
+        )
+    }
+}
+
+// A column of 16x16 blocks, the unit the world is sent to the client in
+#[derive(Copy, Clone, Debug, Default, PartialOrd, PartialEq, Ord, Eq, Hash)]
+pub struct ChunkPos {
+    pub x: i32,
+    pub z: i32,
+}
+
+impl ChunkPos {
+    // the lowest corner of the chunk, at y = 0
+    pub fn origin(&self) -> Result<Position> {
+        Position::new(self.x << 4, 0, self.z << 4)
+    }
+}
+
+// A 16x16x16 cube of blocks within a chunk
+#[derive(Copy, Clone, Debug, Default, PartialOrd, PartialEq, Ord, Eq, Hash)]
+pub struct SectionPos {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl SectionPos {
+    pub fn chunk(&self) -> ChunkPos {
+        ChunkPos {
+            x: self.x,
+            z: self.z,
+        }
+    }
+
+    // the lowest corner of the section
+    pub fn origin(&self) -> Result<Position> {
+        Position::new(self.x << 4, self.y << 4, self.z << 4)
+    }
+
+    // inverse of Position::in_section()
+    pub fn block(&self, x: u8, y: u8, z: u8) -> Result<Position> {
+        self.origin()?
+            .offset((x & 15).into(), (y & 15).into(), (z & 15).into())
+    }
+}
+
+// The faces of a block, in the order the protocol numbers them
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Down,  // -y
+    Up,    // +y
+    North, // -z
+    South, // +z
+    West,  // -x
+    East,  // +x
+}
+
+impl Direction {
+    pub const ALL: [Direction; 6] = [
+        Direction::Down,
+        Direction::Up,
+        Direction::North,
+        Direction::South,
+        Direction::West,
+        Direction::East,
+    ];
+
+    pub fn offset(self) -> (i32, i32, i32) {
+        match self {
+            Self::Down => (0, -1, 0),
+            Self::Up => (0, 1, 0),
+            Self::North => (0, 0, -1),
+            Self::South => (0, 0, 1),
+            Self::West => (-1, 0, 0),
+            Self::East => (1, 0, 0),
+        }
+    }
+
+    pub fn opposite(self) -> Self {
+        match self {
+            Self::Down => Self::Up,
+            Self::Up => Self::Down,
+            Self::North => Self::South,
+            Self::South => Self::North,
+            Self::West => Self::East,
+            Self::East => Self::West,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.get(usize::from(id)).copied()
+    }
+
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+}
+
+// #[serde(with = "digmake::se::legacy_position")]
+// for talking to servers older than 1.14, which use PositionLayout::XYZ
+pub mod legacy_position {
+    use super::{Position, PositionLayout};
+    use serde::{de, ser, Deserialize};
+
+    pub fn serialize<S>(position: &Position, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        let data = position
+            .encode(PositionLayout::XYZ)
+            .map_err(ser::Error::custom)?;
+        serializer.serialize_i64(data)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Position, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let data = i64::deserialize(deserializer)?;
+        Ok(Position::decode(data, PositionLayout::XYZ))
+    }
+}
+
+// A rotation in steps of 1/256 of a full turn
+#[derive(Copy, Clone, Debug, Default, PartialOrd, PartialEq, Ord, Eq, Hash)]
+pub struct Angle(pub u8);
+
+impl Angle {
+    const STEPS_PER_DEGREE: f32 = 256.0 / 360.0;
+
+    // wraps around, so -90 and 270 are the same angle
+    pub fn from_degrees(degrees: f32) -> Self {
+        let steps = (degrees * Self::STEPS_PER_DEGREE).round() as i64;
+        Self(steps.rem_euclid(256) as u8)
+    }
+
+    // in the range [0, 360)
+    pub fn to_degrees(self) -> f32 {
+        self.0 as f32 / Self::STEPS_PER_DEGREE
+    }
+}
+
+// An entity velocity along one axis, in units of 1/8000 of a block per tick
+#[derive(Copy, Clone, Debug, Default, PartialOrd, PartialEq, Ord, Eq, Hash)]
+pub struct Velocity(pub i16);
+
+impl Velocity {
+    const UNITS_PER_BLOCK: f64 = 8000.0;
+
+    // the vanilla server clamps velocities to about 4 blocks per tick, and so do we
+    pub fn from_blocks_per_tick(blocks: f64) -> Self {
+        let units = (blocks * Self::UNITS_PER_BLOCK).round();
+        Self(units.max(i16::MIN as f64).min(i16::MAX as f64) as i16)
+    }
+
+    pub fn to_blocks_per_tick(self) -> f64 {
+        self.0 as f64 / Self::UNITS_PER_BLOCK
+    }
+}
+
+// A change in entity position along one axis, in units of 1/4096 of a block
+// Movements too large to fit (8 blocks or more) must be sent as a teleport instead
+#[derive(Copy, Clone, Debug, Default, PartialOrd, PartialEq, Ord, Eq, Hash)]
+pub struct PositionDelta(pub i16);
+
+impl PositionDelta {
+    const UNITS_PER_BLOCK: f64 = 4096.0;
+
+    // None if the movement is too large
+    pub fn from_blocks(blocks: f64) -> Option<Self> {
+        let units = (blocks * Self::UNITS_PER_BLOCK).round();
+        if units < i16::MIN as f64 || units > i16::MAX as f64 {
+            return None;
+        }
+
+        Some(Self(units as i16))
+    }
+
+    // the delta the vanilla server sends when an entity moves from prev to current
+    pub fn between(prev: f64, current: f64) -> Option<Self> {
+        let units =
+            (current * Self::UNITS_PER_BLOCK).round() - (prev * Self::UNITS_PER_BLOCK).round();
+        Self::from_blocks(units / Self::UNITS_PER_BLOCK)
+    }
+
+    pub fn to_blocks(self) -> f64 {
+        self.0 as f64 / Self::UNITS_PER_BLOCK
+    }
+}
+
+// A String with a maximum length, counted in UTF-16 code units like the vanilla implementation.
+// The length is checked on both serialization and deserialization.
+// S can be String, or &str to borrow from the input.
+#[derive(Clone, Default, PartialOrd, PartialEq, Ord, Eq, Hash)]
+pub struct BoundedStr<S, const N: usize>(pub S);
+
+pub type Username<S> = BoundedStr<S, 16>;
+pub type Hostname<S> = BoundedStr<S, 255>;
+pub type ChatStr<S> = BoundedStr<S, 32767>;
+
+impl<S: AsRef<str>, const N: usize> BoundedStr<S, N> {
+    pub const fn max_len() -> usize {
+        N
+    }
+
+    pub fn new(s: S) -> Result<Self> {
+        let this = Self(s);
+        this.check()?;
+        Ok(this)
+    }
+
+    pub fn check(&self) -> Result<()> {
+        let len = self.0.as_ref().encode_utf16().count();
+        if len > N {
+            return Err(Error::Other(format!(
+                "string of length {} is longer than the maximum of {}",
+                len, N
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> S {
+        self.0
+    }
+}
+
+impl<S: AsRef<str>, const N: usize> std::ops::Deref for BoundedStr<S, N> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.0.as_ref()
+    }
+}
+
+impl<S: AsRef<str>, const N: usize> std::fmt::Debug for BoundedStr<S, N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self.0.as_ref(), f)
+    }
+}
+
+impl<S: AsRef<str>, const N: usize> std::fmt::Display for BoundedStr<S, N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self.0.as_ref(), f)
+    }
+}
+
+// A namespaced location, such as minecraft:stone or digmake:debug/channel
+// Stored as a String on the wire, the namespace defaults to minecraft if it is left out
+#[derive(Clone, PartialOrd, PartialEq, Ord, Eq, Hash)]
+pub struct Identifier {
+    pub namespace: String,
+    pub path: String,
+}
+
+impl Identifier {
+    pub const DEFAULT_NAMESPACE: &'static str = "minecraft";
+
+    pub fn new(namespace: &str, path: &str) -> Result<Self> {
+        Self::check(namespace, path)?;
+        Ok(Self {
+            namespace: namespace.to_owned(),
+            path: path.to_owned(),
+        })
+    }
+
+    pub fn minecraft(path: &str) -> Result<Self> {
+        Self::new(Self::DEFAULT_NAMESPACE, path)
+    }
+
+    // namespaces may only use [a-z0-9.-_], paths additionally may use /
+    fn check(namespace: &str, path: &str) -> Result<()> {
+        let valid = |c: char, extra: &[char]| {
+            c.is_ascii_lowercase() || c.is_ascii_digit() || "._-".contains(c) || extra.contains(&c)
+        };
+
+        if namespace.is_empty() || !namespace.chars().all(|c| valid(c, &[])) {
+            return Err(Error::Other(format!(
+                "invalid identifier namespace {:?}",
+                namespace
+            )));
+        }
+
+        if path.is_empty() || !path.chars().all(|c| valid(c, &['/'])) {
+            return Err(Error::Other(format!("invalid identifier path {:?}", path)));
+        }
+
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for Identifier {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.split_once(':') {
+            // like vanilla, ":stone" is minecraft:stone
+            Some(("", path)) => Self::minecraft(path),
+            Some((namespace, path)) => Self::new(namespace, path),
+            None => Self::minecraft(s),
+        }
+    }
+}
+
+impl std::fmt::Debug for Identifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "\"{}\"", self)
+    }
+}
+
+impl std::fmt::Display for Identifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.namespace, self.path)
+    }
+}
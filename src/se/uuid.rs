@@ -0,0 +1,136 @@
+// UUIDs: se::Uuid for packet fields, the uuid of an offline player, and the int-array and
+// hyphenated forms used in NBT and JSON
+
+use serde::{de, ser, Deserialize, Serialize};
+
+// A UUID as the protocol sends it, one big-endian 128-bit number
+#[derive(Copy, Clone, Debug, Default, PartialOrd, PartialEq, Ord, Eq, Hash)]
+pub struct Uuid(pub ::uuid::Uuid);
+
+impl From<::uuid::Uuid> for Uuid {
+    fn from(uuid: ::uuid::Uuid) -> Self {
+        Self(uuid)
+    }
+}
+
+impl From<Uuid> for ::uuid::Uuid {
+    fn from(uuid: Uuid) -> Self {
+        uuid.0
+    }
+}
+
+impl std::fmt::Display for Uuid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl Serialize for Uuid {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        // formats like json get the hyphenated form
+        if serializer.is_human_readable() {
+            return self.0.serialize(serializer);
+        }
+
+        serializer.serialize_u128(self.0.as_u128())
+    }
+}
+
+impl<'de> Deserialize<'de> for Uuid {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        // the hyphenated form, like Serialize writes for formats like json
+        if deserializer.is_human_readable() {
+            return ::uuid::Uuid::deserialize(deserializer).map(Uuid);
+        }
+
+        let data = u128::deserialize(deserializer)?;
+        Ok(Uuid(::uuid::Uuid::from_u128(data)))
+    }
+}
+
+// the uuid a server in offline mode gives a player, a version 3 uuid of "OfflinePlayer:<name>"
+pub fn offline_player_uuid(username: &str) -> ::uuid::Uuid {
+    let bytes = md5::compute(format!("OfflinePlayer:{}", username)).into();
+
+    ::uuid::Builder::from_bytes(bytes)
+        .set_variant(::uuid::Variant::RFC4122)
+        .set_version(::uuid::Version::Md5)
+        .build()
+}
+
+// most significant int first, as stored in NBT
+pub fn to_int_array(uuid: &::uuid::Uuid) -> [i32; 4] {
+    let bytes = uuid.as_bytes();
+    let mut ints = [0i32; 4];
+    for (int, chunk) in ints.iter_mut().zip(bytes.chunks_exact(4)) {
+        *int = i32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+    ints
+}
+
+pub fn from_int_array(ints: [i32; 4]) -> ::uuid::Uuid {
+    let mut bytes = [0u8; 16];
+    for (chunk, int) in bytes.chunks_exact_mut(4).zip(ints.iter()) {
+        chunk.copy_from_slice(&int.to_be_bytes());
+    }
+    ::uuid::Uuid::from_bytes(bytes)
+}
+
+// #[serde(with = "digmake::se::uuid::int_array")]
+// an NBT IntArray of 4 ints, or a list of 4 numbers in JSON
+pub mod int_array {
+    use super::{from_int_array, to_int_array};
+    use ::uuid::Uuid;
+    use serde::de::{Deserialize, Deserializer, Error};
+    use serde::ser::Serializer;
+
+    pub fn serialize<S>(uuid: &Uuid, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        nbt::i32_array(to_int_array(uuid).iter(), serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Uuid, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let ints = Vec::<i32>::deserialize(deserializer)?;
+        match ints[..] {
+            [a, b, c, d] => Ok(from_int_array([a, b, c, d])),
+            _ => Err(D::Error::invalid_length(ints.len(), &"4 ints")),
+        }
+    }
+}
+
+// #[serde(with = "digmake::se::uuid::hyphenated")]
+// always a string like 069a79f4-44e9-4726-a5be-fca90e38aaf5, even in the binary protocol
+pub mod hyphenated {
+    use ::uuid::Uuid;
+    use serde::de::{Deserialize, Deserializer, Error};
+    use serde::ser::Serializer;
+
+    pub fn serialize<S>(uuid: &Uuid, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(
+            uuid.to_hyphenated()
+                .encode_lower(&mut Uuid::encode_buffer()),
+        )
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Uuid, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let text = String::deserialize(deserializer)?;
+        Uuid::parse_str(&text).map_err(D::Error::custom)
+    }
+}
@@ -132,36 +132,28 @@ pub fn test_bytearry() {
 }
 
 pub fn test_uuid() {
+    use digmake::se::Uuid;
     use serde::{self, Deserialize};
 
-    #[derive(Deserialize)]
-    #[serde(remote = "uuid::Uuid")]
-    struct Uuid(
-        #[serde(getter = "uuid::Uuid::as_bytes")] [u8; 16], //
-    );
-
-    impl From<Uuid> for uuid::Uuid {
-        fn from(uuid: Uuid) -> Self {
-            Self::from_bytes(uuid.0)
-        }
-    }
-
     #[derive(Deserialize, Debug)]
     struct Struct {
-        #[serde(with = "Uuid")]
-        value: uuid::Uuid,
+        value: Uuid,
+        rest: u8,
     }
 
     let DATA = vec![
         63, 231, 92, 12, //
         63, 231, 92, 12, //
         63, 231, 92, 12, //
-        63, 231, 92, 12,
+        63, 231, 92, 12, //
+        0xFF,
     ];
 
     let data = from_bytes_debug::<Struct>(&DATA);
     dbg!(&data);
-    dbg!(data.1.unwrap());
+    let data = data.1.unwrap();
+    assert_eq!(data.value.0.as_bytes(), &DATA[..16]);
+    assert_eq!(data.rest, 0xFF);
 }
 
 /*
@@ -202,3 +194,1837 @@ pub fn test_vec() {
     dbg!(&data);
     dbg!(data.1.unwrap());
 }
+
+pub fn test_map() {
+    use digmake::se::{from_bytes, serialize};
+    use std::collections::BTreeMap;
+
+    let mut map = BTreeMap::new();
+    map.insert(String::from("minecraft:stone"), VarInt(1));
+    map.insert(String::from("minecraft:dirt"), VarInt(300));
+
+    let DATA = serialize(&map).unwrap();
+    dbg!(&DATA);
+
+    assert_eq!(
+        DATA,
+        vec![
+            2, // number of entries
+            14, b'm', b'i', b'n', b'e', b'c', b'r', b'a', b'f', b't', b':', b'd', b'i', b'r', b't',
+            0xac, 0x02, // 300
+            15, b'm', b'i', b'n', b'e', b'c', b'r', b'a', b'f', b't', b':', b's', b't', b'o', b'n',
+            b'e', //
+            0x01,
+        ]
+    );
+
+    let data = from_bytes::<BTreeMap<String, VarInt>>(&DATA);
+    dbg!(&data);
+    assert_eq!(data.unwrap(), map);
+}
+
+// counts the writes, like the syscalls of a file or socket
+struct Writes(Vec<usize>);
+
+impl std::io::Write for Writes {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.push(buf.len());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+pub fn test_serialize_into() {
+    use digmake::se::{serialize, serialize_into, serialize_to_writer};
+    use serde::{Serialize, Serializer};
+
+    // an iterator without an exact size hint, so the length must be buffered instead of streamed
+    struct Evens(u8);
+
+    impl Serialize for Evens {
+        fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+            ser.collect_seq((0..self.0).filter(|x| x % 2 == 0))
+        }
+    }
+
+    #[derive(Serialize)]
+    struct Struct {
+        foo: Vec<(i8, i16)>,
+        evens: Evens,
+    }
+
+    let data = Struct {
+        foo: vec![(1, 0x0203), (4, 0x0506)],
+        evens: Evens(7),
+    };
+
+    let expected = vec![
+        2, // len of foo
+        1, 2, 3, // foo[0]
+        4, 5, 6, // foo[1]
+        4, // len of evens
+        0, 2, 4, 6,
+    ];
+
+    assert_eq!(serialize(&data).unwrap(), expected);
+
+    let mut buffer = Vec::with_capacity(64);
+    for _ in 0..3 {
+        buffer.clear();
+        serialize_into(&mut buffer, &data).unwrap();
+        assert_eq!(buffer, expected);
+    }
+
+    let mut writer = std::io::Cursor::new(Vec::new());
+    serialize_to_writer(&mut writer, &data).unwrap();
+    dbg!(&writer);
+    assert_eq!(writer.into_inner(), expected);
+
+    let mut writer = Writes(Vec::new());
+    serialize_to_writer(&mut writer, (VarInt(300), vec![7u8; 1000])).unwrap();
+    assert_eq!(writer.0, vec![1004]);
+
+    // says it has 3 elements, but only has 2, which is found after they have been written
+    struct Liar;
+
+    impl Serialize for Liar {
+        fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+            use serde::ser::SerializeSeq;
+
+            let mut seq = ser.serialize_seq(Some(3))?;
+            seq.serialize_element(&1u8)?;
+            seq.serialize_element(&2u8)?;
+            seq.end()
+        }
+    }
+
+    buffer.clear();
+    buffer.push(0xAA);
+    assert!(serialize_into(&mut buffer, &Liar).is_err());
+    assert_eq!(buffer, vec![0xAA]);
+}
+
+pub fn test_encoded_len() {
+    use digmake::se::{encoded_len, serialize};
+    use serde::Serialize;
+    use std::collections::HashMap;
+
+    #[derive(Serialize)]
+    struct Struct<'a> {
+        id: VarInt,
+        name: &'a str,
+        values: Vec<Option<(u8, VarInt)>>,
+        map: HashMap<&'a str, i64>,
+    }
+
+    let mut map = HashMap::new();
+    map.insert("alfa", 1);
+    map.insert("bravo", -1);
+
+    let data = Struct {
+        id: VarInt(-1),
+        name: "localhost",
+        values: (0..200)
+            .map(|i| Some((i, VarInt(i as i32 * 1000))))
+            .collect(),
+        map,
+    };
+
+    let len = encoded_len(&data).unwrap();
+    dbg!(len);
+    assert_eq!(len, serialize(&data).unwrap().len());
+
+    for &num in &[
+        0,
+        1,
+        127,
+        128,
+        255,
+        25565,
+        2097151,
+        2097152,
+        i32::MAX,
+        -1,
+        i32::MIN,
+    ] {
+        assert_eq!(
+            VarInt(num).encoded_len(),
+            serialize(VarInt(num)).unwrap().len()
+        );
+    }
+}
+
+pub fn test_write_packet() {
+    use digmake::write_packet;
+
+    let mut output = Vec::new();
+    write_packet(&mut output, 0x1F, &0x0123456789ABCDEFi64).unwrap();
+    dbg!(&output);
+    assert_eq!(
+        output,
+        vec![9, 0x1F, 0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF]
+    );
+
+    // in one write
+    let mut writer = Writes(Vec::new());
+    write_packet(&mut writer, 0x24, &vec![7u8; 1000]).unwrap();
+    assert_eq!(writer.0, vec![1005]);
+
+    let too_big = vec![0u8; digmake::MAX_PACKET_LEN];
+    let result = write_packet(std::io::sink(), 0x20, &serde_bytes::Bytes::new(&too_big));
+    dbg!(&result);
+    assert!(result.is_err());
+}
+
+pub fn test_from_reader() {
+    use digmake::se::{from_reader, serialize};
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+    use std::io::BufReader;
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct Struct {
+        id: VarInt,
+        name: String,
+        values: Vec<Option<(u8, VarInt)>>,
+        map: BTreeMap<String, i64>,
+        rest: serde_bytes::ByteBuf,
+    }
+
+    let mut map = BTreeMap::new();
+    map.insert(String::from("alfa"), 1);
+    map.insert(String::from("bravo"), -1);
+
+    let data = Struct {
+        id: VarInt(-1),
+        name: String::from("localhost"),
+        values: (0..200)
+            .map(|i| Some((i, VarInt(i as i32 * 1000))))
+            .collect(),
+        map,
+        rest: serde_bytes::ByteBuf::from(vec![1, 2, 3]),
+    };
+
+    let DATA = serialize(&data).unwrap();
+
+    // a tiny buffer, so every value straddles the end of it
+    let reader = BufReader::with_capacity(3, &DATA[..]);
+    let result = from_reader::<_, Struct>(reader);
+    dbg!(&result.as_ref().map(|data| &data.name));
+    assert_eq!(result.unwrap(), data);
+
+    let result = from_reader::<_, Struct>(&DATA[..DATA.len() / 2]);
+    dbg!(&result);
+    assert!(result.is_err());
+}
+
+pub fn test_read_packet_streaming() {
+    use digmake::{read_packet_streaming, se::from_reader};
+    use std::io::BufReader;
+
+    let DATA = vec![
+        7, 0x00, // len, packet id
+        5, b'h', b'e', b'l', b'l', b'o', //
+        3, 0x01, 0xAA, 0xBB, // not fully read by the handler
+        2, 0x02, 0x7F,
+    ];
+
+    let mut reader = BufReader::with_capacity(4, &DATA[..]);
+
+    let (id, text) = read_packet_streaming(&mut reader, |id, body| {
+        Ok((id, from_reader::<_, String>(body)?))
+    })
+    .unwrap();
+    assert_eq!((id, text.as_str()), (0x00, "hello"));
+
+    let (id, byte) = read_packet_streaming(&mut reader, |id, body| {
+        Ok((id, from_reader::<_, u8>(body)?))
+    })
+    .unwrap();
+    assert_eq!((id, byte), (0x01, 0xAA));
+
+    let (id, byte) = read_packet_streaming(&mut reader, |id, body| {
+        Ok((id, from_reader::<_, u8>(body)?))
+    })
+    .unwrap();
+    assert_eq!((id, byte), (0x02, 0x7F));
+}
+
+pub fn test_exact() {
+    use digmake::se::{from_bytes_exact, DeError, Error};
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct PlayerMovement {
+        on_ground: bool,
+    }
+
+    let DATA = vec![0x01];
+    assert_eq!(
+        from_bytes_exact::<PlayerMovement>(&DATA).unwrap(),
+        PlayerMovement { on_ground: true }
+    );
+
+    let DATA = vec![0x01, 0xAA, 0xBB];
+    let error = from_bytes_exact::<PlayerMovement>(&DATA).unwrap_err();
+    dbg!(&error);
+
+    match &error.diagnostic().unwrap().error {
+        Error::SerdeDe(DeError::TrailingData { offset, rest }) => {
+            assert_eq!(*offset, 1);
+            assert_eq!(rest, &vec![0xAA, 0xBB]);
+        }
+        _ => panic!("expected trailing data error"),
+    }
+}
+
+pub fn test_diagnostic() {
+    use digmake::se::{from_bytes, DeError, Error};
+
+    #[derive(Deserialize, Debug)]
+    struct Handshake<'a> {
+        protocol_version: VarInt,
+        address: &'a str,
+        port: u16,
+        next_state: VarInt,
+    }
+
+    let DATA = vec![
+        0xf2, 0x05, // protocol_version
+        0x09, // address len
+        0x6c, 0x6f, 0x63, 0x61, 0xff, 0x68, 0x6f, 0x73, 0x74, // address, with invalid utf8
+        0x63, 0xdd, // port
+        0x01, // next_state
+    ];
+
+    let error = from_bytes::<Handshake>(&DATA).unwrap_err();
+    println!("{}", error);
+    println!("{:?}", error.diagnostic().unwrap());
+
+    let diagnostic = error.diagnostic().unwrap();
+    assert_eq!(diagnostic.short_type_name(), "Handshake");
+    assert_eq!(diagnostic.path_string(), "Handshake.address");
+    assert_eq!((diagnostic.offset, diagnostic.len), (3, 9));
+    assert_eq!(diagnostic.input(), &DATA[..]);
+    assert!(matches!(
+        diagnostic.error,
+        Error::SerdeDe(DeError::Parse { .. })
+    ));
+}
+
+pub fn test_diagnostic_path() {
+    use digmake::se::{from_bytes, from_reader, PathSegment};
+
+    #[derive(Deserialize, Debug)]
+    enum Flags {
+        None,
+        Background { texture: String },
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct Display {
+        title: String,
+        flags: Flags,
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct Advancement {
+        name: String,
+        display: Option<Display>,
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct Advancements {
+        reset_clear: bool,
+        advancements: Vec<Advancement>,
+    }
+
+    let DATA = vec![
+        0x01, // reset_clear
+        0x02, // advancements len
+        0x01, b'a', // advancements[0].name
+        0x00, // advancements[0].display
+        0x01, b'b', // advancements[1].name
+        0x01, // advancements[1].display
+        0x01, b'c', // advancements[1].display.title
+        0x01, // advancements[1].display.flags::Background
+        0x02, b'd',
+        0xff, // advancements[1].display.flags::Background.texture, with invalid utf8
+    ];
+
+    let error = from_bytes::<Advancements>(&DATA).unwrap_err();
+    println!("{:?}", error.diagnostic().unwrap());
+
+    let diagnostic = error.diagnostic().unwrap();
+    assert_eq!(
+        diagnostic.path_string(),
+        "Advancements.advancements[1].display.flags::Background.texture"
+    );
+    assert_eq!(diagnostic.path[1], PathSegment::Index(1));
+
+    let error = from_reader::<_, Advancements>(&DATA[..]).unwrap_err();
+    println!("{}", error);
+    assert_eq!(
+        error.diagnostic().unwrap().path_string(),
+        "Advancements.advancements[1].display.flags::Background.texture"
+    );
+}
+
+pub fn test_strict() {
+    use digmake::se::{from_bytes, from_bytes_with_config, from_reader_with_config};
+    use digmake::se::{DeError, DeserializerConfig, Error};
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Packet {
+        on_ground: bool,
+        entity_id: VarInt,
+        name: String,
+    }
+
+    let strict = DeserializerConfig::strict();
+
+    let DATA = vec![0x01, 0x05, 0x01, 0x61];
+    let packet = from_bytes_with_config::<Packet>(&DATA, strict).unwrap();
+    assert_eq!(packet.entity_id, VarInt(5));
+
+    let check = |DATA: &[u8], offset: usize, len: usize| {
+        // still accepted by default
+        assert!(from_bytes::<Packet>(DATA).is_ok());
+
+        let error = from_bytes_with_config::<Packet>(DATA, strict).unwrap_err();
+        dbg!(&error);
+        match &error.diagnostic().unwrap().error {
+            Error::SerdeDe(DeError::Parse {
+                offset: o, len: l, ..
+            }) => assert_eq!((*o, *l), (offset, len)),
+            _ => panic!("expected parse error"),
+        }
+
+        let error = from_reader_with_config::<_, Packet>(DATA, strict).unwrap_err();
+        match &error.diagnostic().unwrap().error {
+            Error::SerdeDe(DeError::Parse {
+                offset: o, len: l, ..
+            }) => assert_eq!((*o, *l), (offset, len)),
+            _ => panic!("expected parse error"),
+        }
+    };
+
+    // bool that is not 0 or 1
+    check(&[0x02, 0x05, 0x01, 0x61], 0, 1);
+    // VarInt 5 padded out to 3 bytes
+    check(&[0x01, 0x85, 0x80, 0x00, 0x01, 0x61], 1, 3);
+    // string length padded out to 2 bytes
+    check(&[0x01, 0x05, 0x81, 0x00, 0x61], 2, 2);
+
+    // string longer than the protocol allows
+    let mut DATA = vec![0x01, 0x05];
+    DATA.extend(digmake::se::serialize("a".repeat(32768)).unwrap());
+    assert!(from_bytes::<Packet>(&DATA).is_ok());
+    assert!(from_bytes_with_config::<Packet>(&DATA, strict).is_err());
+    assert!(from_reader_with_config::<_, Packet>(&DATA[..], strict).is_err());
+}
+
+pub fn test_bounded_str() {
+    use digmake::se::{
+        from_bytes, from_reader, serialize, BoundedStr, Hostname, Identifier, Username,
+    };
+
+    #[derive(Deserialize, serde::Serialize, Debug, PartialEq)]
+    struct Handshake<'a> {
+        #[serde(borrow)]
+        address: Hostname<&'a str>,
+        channel: Identifier,
+    }
+
+    let DATA = vec![
+        0x09, 0x6c, 0x6f, 0x63, 0x61, 0x6c, 0x68, 0x6f, 0x73, 0x74, // localhost
+        0x0A, 0x62, 0x72, 0x61, 0x6e, 0x64, 0x3a, 0x74, 0x65, 0x73, 0x74, // brand:test
+    ];
+    let packet = from_bytes::<Handshake>(&DATA).unwrap();
+    dbg!(&packet);
+    assert_eq!(&*packet.address, "localhost");
+    assert_eq!(packet.channel, Identifier::new("brand", "test").unwrap());
+    assert_eq!(serialize(&packet).unwrap(), DATA);
+
+    // namespace defaults to minecraft
+    let id: Identifier = "stone".parse().unwrap();
+    assert_eq!(id.to_string(), "minecraft:stone");
+    assert!("minecraft:Stone".parse::<Identifier>().is_err());
+    assert!("minecraft:".parse::<Identifier>().is_err());
+    assert_eq!(
+        ":stone".parse::<Identifier>().unwrap(),
+        Identifier::minecraft("stone").unwrap()
+    );
+    assert!(":".parse::<Identifier>().is_err());
+    assert_eq!(
+        from_reader::<_, Identifier>(&serialize("block/oak_log").unwrap()[..]).unwrap(),
+        Identifier::minecraft("block/oak_log").unwrap()
+    );
+
+    // too long in either direction
+    let name = String::from("abcdefghijklmnopq");
+    assert!(Username::new(name.as_str()).is_err());
+    assert!(serialize(BoundedStr::<_, 16>(name.as_str())).is_err());
+    assert!(from_bytes::<Username<&str>>(&serialize(&name).unwrap()).is_err());
+    assert!(from_bytes::<Username<&str>>(&serialize(&name[1..]).unwrap()).is_ok());
+}
+
+pub fn test_uuid_forms() {
+    use digmake::se::{from_bytes, from_reader, serialize, uuid as se_uuid, Uuid};
+
+    #[derive(Deserialize, serde::Serialize, Debug, PartialEq)]
+    struct Struct {
+        value: Uuid,
+        #[serde(with = "se_uuid::hyphenated")]
+        text: uuid::Uuid,
+        rest: u8,
+    }
+
+    let uuid = uuid::Uuid::parse_str("069a79f4-44e9-4726-a5be-fca90e38aaf5").unwrap();
+    let data = Struct {
+        value: Uuid(uuid),
+        text: uuid,
+        rest: 0xFF,
+    };
+
+    let DATA = serialize(&data).unwrap();
+    dbg!(&DATA);
+    assert_eq!(&DATA[..16], uuid.as_bytes());
+    assert_eq!(DATA[16], 36);
+    assert_eq!(&DATA[17..53], b"069a79f4-44e9-4726-a5be-fca90e38aaf5");
+    assert_eq!(from_bytes::<Struct>(&DATA).unwrap(), data);
+    assert_eq!(from_reader::<_, Struct>(&DATA[..]).unwrap(), data);
+    let json = serde_json::to_value(&data).unwrap();
+    assert_eq!(json["value"], "069a79f4-44e9-4726-a5be-fca90e38aaf5");
+    assert_eq!(serde_json::from_value::<Struct>(json).unwrap(), data);
+    assert_eq!(
+        from_bytes::<(Uuid, u8)>(&DATA[..17]).unwrap(),
+        (Uuid(uuid), 36)
+    );
+
+    let ints = se_uuid::to_int_array(&uuid);
+    assert_eq!(ints, [110787060, 1156138790, -1514210135, 238594805]);
+    assert_eq!(se_uuid::from_int_array(ints), uuid);
+
+    assert_eq!(
+        se_uuid::offline_player_uuid("dzil1234").to_string(),
+        "b4bcabdd-6041-360c-84de-bb50c9a8b0b6"
+    );
+}
+
+pub fn test_angle() {
+    use digmake::se::{from_bytes, serialize, Angle};
+
+    assert_eq!(Angle::from_degrees(0.0), Angle(0));
+    assert_eq!(Angle::from_degrees(90.0), Angle(64));
+    assert_eq!(Angle::from_degrees(-90.0), Angle(192));
+    assert_eq!(Angle::from_degrees(360.0), Angle(0));
+    assert_eq!(Angle(128).to_degrees(), 180.0);
+
+    // every encoded value survives a trip through degrees
+    for i in 0..=255 {
+        let angle = Angle(i);
+        assert_eq!(Angle::from_degrees(angle.to_degrees()), angle);
+        assert_eq!(serialize(angle).unwrap(), vec![i]);
+        assert_eq!(from_bytes::<Angle>(&[i]).unwrap(), angle);
+    }
+}
+
+pub fn test_velocity() {
+    use digmake::se::{from_bytes, serialize, PositionDelta, Velocity};
+
+    assert_eq!(Velocity::from_blocks_per_tick(1.0), Velocity(8000));
+    assert_eq!(Velocity::from_blocks_per_tick(-0.5), Velocity(-4000));
+    assert_eq!(Velocity::from_blocks_per_tick(100.0), Velocity(i16::MAX));
+    assert_eq!(Velocity(-8000).to_blocks_per_tick(), -1.0);
+
+    assert_eq!(PositionDelta::from_blocks(1.0), Some(PositionDelta(4096)));
+    assert_eq!(PositionDelta::from_blocks(8.0), None);
+    assert_eq!(
+        PositionDelta::between(10.5, 10.25),
+        Some(PositionDelta(-1024))
+    );
+    assert_eq!(PositionDelta(2048).to_blocks(), 0.5);
+
+    for &i in &[i16::MIN, -1, 0, 1, 12345, i16::MAX] {
+        let velocity = Velocity(i);
+        assert_eq!(
+            Velocity::from_blocks_per_tick(velocity.to_blocks_per_tick()),
+            velocity
+        );
+        let delta = PositionDelta(i);
+        assert_eq!(PositionDelta::from_blocks(delta.to_blocks()), Some(delta));
+
+        let DATA = serialize(velocity).unwrap();
+        assert_eq!(DATA, i.to_be_bytes());
+        assert_eq!(from_bytes::<Velocity>(&DATA).unwrap(), velocity);
+        assert_eq!(from_bytes::<PositionDelta>(&DATA).unwrap(), delta);
+    }
+}
+
+pub fn test_position_layout() {
+    use digmake::se::{
+        from_bytes, legacy_position, serialize, Direction, Position, PositionLayout,
+    };
+
+    let position = Position::new(-1, -2, 3).unwrap();
+    let DATA = serialize(position).unwrap();
+    dbg!(&DATA);
+    assert_eq!(DATA, vec![0xFF, 0xFF, 0xFF, 0xC0, 0x00, 0x00, 0x3F, 0xFE]);
+    assert_eq!(from_bytes::<Position>(&DATA).unwrap(), position);
+
+    #[derive(Deserialize, serde::Serialize, Debug, PartialEq)]
+    struct Legacy(#[serde(with = "legacy_position")] Position);
+
+    let DATA = serialize(Legacy(position)).unwrap();
+    assert_eq!(DATA, vec![0xFF, 0xFF, 0xFF, 0xFF, 0xF8, 0x00, 0x00, 0x03]);
+    assert_eq!(from_bytes::<Legacy>(&DATA).unwrap(), Legacy(position));
+
+    assert_eq!(
+        PositionLayout::for_protocol_version(404),
+        PositionLayout::XYZ
+    );
+    assert_eq!(
+        PositionLayout::for_protocol_version(754),
+        PositionLayout::XZY
+    );
+
+    // the extremes survive, one past them is an error instead of wrapping around
+    for &(x, y, z) in &[
+        (Position::MIN_XZ, Position::MIN_Y, Position::MIN_XZ),
+        (Position::MAX_XZ, Position::MAX_Y, Position::MAX_XZ),
+    ] {
+        let position = Position { x, y, z };
+        for &layout in &[PositionLayout::XZY, PositionLayout::XYZ] {
+            let data = position.encode(layout).unwrap();
+            assert_eq!(Position::decode(data, layout), position);
+        }
+    }
+    assert!(Position::new(Position::MAX_XZ + 1, 0, 0).is_err());
+    assert!(Position::new(0, 2048, 0).is_err());
+    let overflow = Position {
+        x: 0,
+        y: 0,
+        z: Position::MIN_XZ - 1,
+    };
+    assert!(serialize(overflow).is_err());
+
+    let position = Position::new(-17, 70, 33).unwrap();
+    assert_eq!((position.chunk().x, position.chunk().z), (-2, 2));
+    let section = position.section();
+    assert_eq!((section.x, section.y, section.z), (-2, 4, 2));
+    let (x, y, z) = position.in_section();
+    assert_eq!((x, y, z), (15, 6, 1));
+    assert_eq!(section.block(x, y, z).unwrap(), position);
+
+    assert_eq!(
+        position.step(Direction::East).unwrap(),
+        Position::new(-16, 70, 33).unwrap()
+    );
+    for &direction in &Direction::ALL {
+        let back = position.step(direction).unwrap().step(direction.opposite());
+        assert_eq!(back.unwrap(), position);
+        assert_eq!(Direction::from_id(direction.id()), Some(direction));
+    }
+
+    let top = Position::new(0, Position::MAX_Y.into(), 0).unwrap();
+    let neighbours = top.neighbours();
+    assert!(neighbours[0].is_some());
+    assert!(neighbours[1].is_none());
+}
+
+pub fn test_chat() {
+    use digmake::chat::{ClickAction, Content, Json, StatusResponse, TextComponent};
+    use digmake::se::{from_bytes, serialize};
+
+    let json = r##"{"translate":"chat.type.text","with":[{"text":"Steve","color":"gold",
+        "clickEvent":{"action":"suggest_command","value":"/msg Steve "}},"hi"],
+        "extra":[{"text":"!","bold":true,"color":"#ff0000"}]}"##;
+    let component = TextComponent::from_json(json).unwrap();
+    dbg!(&component);
+
+    match &component.content {
+        Content::Translate { translate, with } => {
+            assert_eq!(translate, "chat.type.text");
+            assert_eq!(with[1], TextComponent::text("hi"));
+            let click = with[0].style.click_event.as_ref().unwrap();
+            assert_eq!(click.action, ClickAction::SuggestCommand);
+        }
+        _ => panic!("expected translate"),
+    }
+
+    assert_eq!(component.to_plain(), "chat.type.text[Steve, hi]!");
+    assert_eq!(component.to_legacy(), "chat.type.text[Steve, hi]§4§l!");
+    assert_eq!(
+        component.to_ansi(),
+        "\x1b[0mchat.type.text[Steve, hi]\x1b[0;38;2;255;0;0;1m!\x1b[0m"
+    );
+
+    // the protocol stores it as a json string
+    let DATA = serialize(&component).unwrap();
+    assert_eq!(
+        from_bytes::<String>(&DATA).unwrap(),
+        component.to_json().unwrap()
+    );
+    assert_eq!(from_bytes::<TextComponent>(&DATA).unwrap(), component);
+
+    // children inherit styles, plain strings and lists are shorthand
+    let component = TextComponent::from_json(r#"["a",{"text":"b","color":"red"},"c"]"#).unwrap();
+    let nested = TextComponent::text("")
+        .color("red")
+        .bold()
+        .push(TextComponent::text("x"))
+        .push(TextComponent::text("y").color("blue"));
+    assert_eq!(component.to_legacy(), "a§cb§rc");
+    assert_eq!(nested.to_legacy(), "§c§lx§9§ly");
+    assert_eq!(
+        TextComponent::from_json(r#""plain""#).unwrap(),
+        TextComponent::text("plain")
+    );
+
+    let json = r#"{"version":{"name":"1.16.4","protocol":754},
+        "players":{"max":20,"online":1,"sample":[{"name":"dzil1234","id":"b4bcabdd-6041-360c-84de-bb50c9a8b0b6"}]},
+        "description":{"text":"A Minecraft Server"}}"#;
+    let DATA = serialize(json).unwrap();
+    let status = from_bytes::<Json<StatusResponse>>(&DATA).unwrap().0;
+    dbg!(&status);
+    assert_eq!(status.version.protocol, 754);
+    assert_eq!(status.players.sample[0].name, "dzil1234");
+    assert_eq!(status.description.to_plain(), "A Minecraft Server");
+}
+
+pub fn test_capture() {
+    use digmake::capture::{CaptureReader, CaptureWriter};
+    use digmake::logic::{PacketDirection, State};
+    use serde::Serialize;
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct KeepAlive(i64);
+
+    let mut writer = CaptureWriter::new(Vec::new(), Some(754)).unwrap();
+    writer
+        .write(
+            PacketDirection::Serverbound,
+            State::Handshake,
+            &[0x00, 0x01],
+        )
+        .unwrap();
+    writer
+        .write_packet(
+            PacketDirection::Clientbound,
+            State::Play,
+            0x1F,
+            &KeepAlive(-2),
+        )
+        .unwrap();
+    let DATA = writer.into_inner();
+    dbg!(&DATA);
+
+    let mut reader = CaptureReader::new(&DATA[..]).unwrap();
+    assert_eq!(reader.header().protocol_version, VarInt(754));
+
+    let records = reader
+        .by_ref()
+        .collect::<digmake::se::Result<Vec<_>>>()
+        .unwrap();
+    dbg!(&records);
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].direction, PacketDirection::Serverbound);
+    assert_eq!(records[0].to_packet().unwrap(), vec![0x02, 0x00, 0x01]);
+    assert_eq!(records[1].state, State::Play);
+    assert_eq!(records[1].packet_id().unwrap(), 0x1F);
+    assert_eq!(
+        digmake::se::from_bytes::<KeepAlive>(records[1].body().unwrap()).unwrap(),
+        KeepAlive(-2)
+    );
+    assert!(records[0].timestamp() <= records[1].timestamp());
+
+    assert!(CaptureReader::new(&b"not a capture"[..]).is_err());
+    // a truncated record is an error, not the end of the capture
+    assert!(CaptureReader::new(&DATA[..DATA.len() - 1])
+        .unwrap()
+        .any(|record| record.is_err()));
+}
+
+pub fn test_pcap() {
+    use digmake::pcap::read_connection;
+
+    // ethernet + ipv4 + tcp, checksums are not checked
+    fn frame(from_client: bool, seq: u32, syn: bool, payload: &[u8]) -> Vec<u8> {
+        let (src, dst) = if from_client {
+            (50000u16, 25565u16)
+        } else {
+            (25565, 50000)
+        };
+
+        let mut tcp: Vec<u8> = Vec::new();
+        tcp.extend(&src.to_be_bytes());
+        tcp.extend(&dst.to_be_bytes());
+        tcp.extend(&seq.to_be_bytes());
+        tcp.extend(&[
+            0,
+            0,
+            0,
+            0,
+            0x50,
+            if syn { 0x02 } else { 0x18 },
+            0xFF,
+            0xFF,
+            0,
+            0,
+            0,
+            0,
+        ]);
+        tcp.extend(payload);
+
+        let mut ip = vec![0x45, 0];
+        ip.extend(&(20 + tcp.len() as u16).to_be_bytes());
+        ip.extend(&[0, 0, 0x40, 0, 64, 6, 0, 0, 127, 0, 0, 1, 127, 0, 0, 1]);
+        ip.extend(tcp);
+
+        let mut ethernet = vec![0; 12];
+        ethernet.extend(&[0x08, 0x00]);
+        ethernet.extend(ip);
+        ethernet
+    }
+
+    let frames = [
+        frame(true, 99, true, &[]),
+        // the server's sequence numbers wrap around to 0
+        frame(false, 0xFFFF_FFFE, true, &[]),
+        frame(true, 100, false, &[0x02, 0x00]),
+        // retransmitted
+        frame(true, 100, false, &[0x02, 0x00]),
+        // out of order, and overlapping the segment before it
+        frame(true, 103, false, &[0x01, 0x03, 0x04]),
+        frame(true, 102, false, &[0x03, 0x01]),
+        frame(false, 0, false, &[0x05]),
+        // starts before the first byte of data
+        frame(false, 0xFFFF_FFFD, false, &[0xEE, 0xEE, 0x01]),
+        frame(true, 97, false, &[0xEE, 0xEE]),
+    ];
+
+    // little endian pcap with microsecond timestamps
+    let mut DATA = Vec::new();
+    DATA.extend(&0xA1B2C3D4u32.to_le_bytes());
+    DATA.extend(&[
+        2, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xFF, 0xFF, 0, 0, 1, 0, 0, 0,
+    ]);
+    for (i, frame) in frames.iter().enumerate() {
+        DATA.extend(&1_600_000_000u32.to_le_bytes());
+        DATA.extend(&(i as u32 * 1000).to_le_bytes());
+        DATA.extend(&(frame.len() as u32).to_le_bytes());
+        DATA.extend(&(frame.len() as u32).to_le_bytes());
+        DATA.extend(frame);
+    }
+
+    let chunks = read_connection(&DATA, None).unwrap();
+    dbg!(&chunks);
+    assert_eq!(chunks.len(), 2);
+    assert!(!chunks[0].is_server);
+    assert_eq!(chunks[0].data, vec![0x02, 0x00, 0x03, 0x01, 0x03, 0x04]);
+    assert!(chunks[1].is_server);
+    assert_eq!(chunks[1].data, vec![0x01, 0x05]);
+    assert_eq!(chunks[1].timestamp.as_micros(), 1_600_000_000_007_000);
+
+    assert!(read_connection(b"not a capture", None).is_err());
+    assert!(read_connection(&DATA, Some(1234)).is_err());
+}
+
+pub fn test_human_readable() {
+    use digmake::se::{serialize, Angle, Position, VarLong};
+    use serde::Serialize;
+
+    #[derive(Serialize, Debug)]
+    struct Packet {
+        id: VarInt,
+        time: VarLong,
+        position: Position,
+        yaw: Angle,
+    }
+
+    let packet = Packet {
+        id: VarInt(300),
+        time: VarLong(-1),
+        position: Position::new(1, -2, 3).unwrap(),
+        yaw: Angle(64),
+    };
+
+    let DATA = serde_json::to_value(&packet).unwrap();
+    dbg!(&DATA);
+    assert_eq!(
+        DATA,
+        serde_json::json!({
+            "id": 300,
+            "time": -1,
+            "position": {"x": 1, "y": -2, "z": 3},
+            "yaw": 64,
+        })
+    );
+
+    // and read back
+    let position: Position = serde_json::from_value(DATA["position"].clone()).unwrap();
+    assert_eq!(position, packet.position);
+    let far = serde_json::json!({"x": 0, "y": 5000, "z": 0});
+    assert!(serde_json::from_value::<Position>(far).is_err());
+
+    // the wire format is unchanged
+    let encoded = serialize(&packet).unwrap();
+    assert_eq!(&encoded[..2], &[0xAC, 0x02]);
+    assert_eq!(
+        &encoded[2..12],
+        &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01]
+    );
+}
+
+pub fn test_codec() {
+    use digmake::codec::{split_packet_id, Decoder, Encoder};
+
+    let secret = [7u8; 16];
+    let small = vec![0x1F, 1, 2, 3];
+    let mut big = vec![0x22];
+    big.extend(std::iter::repeat_n(0xAB, 1000));
+
+    let mut encoder = Encoder::new();
+    let mut DATA = encoder.encode(&small).unwrap();
+    assert_eq!(DATA, vec![0x04, 0x1F, 1, 2, 3]);
+
+    // everything after this is compressed and encrypted
+    encoder.set_compression(256);
+    encoder.enable_encryption(&secret).unwrap();
+    for frame in &[&small, &big, &small] {
+        DATA.extend(encoder.encode(frame).unwrap());
+    }
+    dbg!(DATA.len());
+    assert!(DATA.len() < 100);
+
+    // the first frame is read before encryption is turned on, with the rest already pushed
+    let mut decoder = Decoder::new();
+    decoder.push(&DATA[..20]);
+    assert_eq!(decoder.next_frame().unwrap(), Some(small.clone()));
+    decoder.set_compression(256);
+    decoder.enable_encryption(&secret).unwrap();
+
+    // the rest arrives in pieces that dont line up with the frames
+    let mut frames = Vec::new();
+    for piece in DATA[20..].chunks(7) {
+        decoder.push(piece);
+        while let Some(frame) = decoder.next_frame().unwrap() {
+            frames.push(frame);
+        }
+    }
+    assert_eq!(frames, vec![small.clone(), big, small]);
+    assert_eq!(decoder.buffered(), 0);
+
+    let (packet_id, body) = split_packet_id(&frames[1]).unwrap();
+    assert_eq!(packet_id, 0x22);
+    assert_eq!(body.len(), 1000);
+
+    assert!(Decoder::new().enable_encryption(&[0; 5]).is_err());
+}
+
+pub fn test_read_yaml() {
+    use digmake::inspector::{read_data, Format};
+
+    let dir = std::env::temp_dir().join(format!("digmake-yaml-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("stream.yaml");
+
+    std::fs::write(
+        &path,
+        "peer0_0: !!binary |\n  AAEC\npeer0_1: AwQ=\npeer1_0: BQ==\n",
+    )
+    .unwrap();
+    let DATA = read_data(&path, Format::Yaml, None).unwrap();
+    assert_eq!(DATA.len(), 2);
+    assert!(!DATA[0].is_server);
+    assert_eq!(DATA[0].data, vec![0, 1, 2, 3, 4]);
+    assert!(DATA[1].is_server);
+    assert_eq!(DATA[1].data, vec![5]);
+
+    // errors instead of panics
+    for bad in &["[1, 2", "- a list", "peer2_0: AAEC", "peer0_0: not base64!"] {
+        std::fs::write(&path, bad).unwrap();
+        let err = read_data(&path, Format::Yaml, None).err();
+        assert!(dbg!(err).is_some());
+    }
+    assert!(read_data(dir.join("missing.yaml"), Format::Yaml, None).is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+pub fn test_inspector_encryption() {
+    use digmake::codec::Encoder;
+    use digmake::inspector::{do_all_data, Data, Inspector, Mode, Options};
+    use digmake::se::serialize;
+
+    let secret = vec![9u8; 16];
+    let frame = |packet_id: i32, body: Vec<u8>| {
+        let mut frame = serialize(VarInt(packet_id)).unwrap();
+        frame.extend(body);
+        frame
+    };
+
+    let mut client = Encoder::new();
+    let mut server = Encoder::new();
+    let mut DATA = Vec::new();
+    let handshake = serialize((VarInt(754), "localhost", 25565u16, VarInt(2))).unwrap();
+    let response = serialize((vec![1u8; 128], vec![2u8; 128])).unwrap();
+    DATA.push(Data::new(
+        false,
+        client.encode(&frame(0x00, handshake)).unwrap(),
+        None,
+    ));
+    DATA.push(Data::new(
+        false,
+        client.encode(&frame(0x01, response)).unwrap(),
+        None,
+    ));
+
+    // the server encrypts and compresses everything after these
+    client.enable_encryption(&secret).unwrap();
+    server.enable_encryption(&secret).unwrap();
+    let compression = server
+        .encode(&frame(0x03, serialize(VarInt(64)).unwrap()))
+        .unwrap();
+    server.set_compression(64);
+    client.set_compression(64);
+    let success = serialize((uuid::Uuid::nil(), "digmake")).unwrap();
+    let mut login = compression;
+    login.extend(server.encode(&frame(0x02, success)).unwrap());
+    login.extend(
+        server
+            .encode(&frame(0x1F, serialize(42i64).unwrap()))
+            .unwrap(),
+    );
+    // split in the middle of a packet
+    let rest = login.split_off(login.len() - 3);
+    DATA.push(Data::new(true, login, None));
+    DATA.push(Data::new(true, rest, None));
+    let mut keep_alive = frame(0x10, serialize(42i64).unwrap());
+    keep_alive.extend(vec![0; 100]);
+    DATA.push(Data::new(false, client.encode(&keep_alive).unwrap(), None));
+
+    let mut inspector = Inspector::new(Options {
+        mode: Mode::Records,
+        secret: Some(secret),
+        ..Options::default()
+    });
+    do_all_data(&DATA, &mut inspector).unwrap();
+    let names: Vec<_> = inspector.records.iter().map(|record| record.name).collect();
+    dbg!(&names);
+    assert_eq!(
+        names,
+        vec![
+            Some("Handshake"),
+            Some("EncryptionResponse"),
+            Some("SetCompression"),
+            Some("LoginSuccess"),
+            Some("KeepAlive"),
+            Some("KeepAlive"),
+        ]
+    );
+    assert_eq!(inspector.records[4].fields, Some(serde_json::json!(42)));
+
+    // without the secret, it stops where encryption starts
+    let mut inspector = Inspector::new(Options {
+        mode: Mode::Records,
+        ..Options::default()
+    });
+    do_all_data(&DATA, &mut inspector).unwrap();
+    assert_eq!(inspector.records.len(), 2);
+}
+
+pub fn test_inspector_disconnect() {
+    use digmake::codec::Encoder;
+    use digmake::inspector::{do_all_data, Data, Inspector, Mode, Options};
+    use digmake::logic::State;
+    use digmake::se::serialize;
+
+    let frame = |packet_id: i32, body: Vec<u8>| {
+        let mut frame = serialize(VarInt(packet_id)).unwrap();
+        frame.extend(body);
+        Encoder::new().encode(&frame).unwrap()
+    };
+
+    let handshake = serialize((VarInt(754), "localhost", 25565u16, VarInt(2))).unwrap();
+    let mut client = frame(0x00, handshake);
+    client.extend(frame(0x00, serialize("digmake").unwrap()));
+    let success = serialize(([0u8; 16], "digmake")).unwrap();
+    let mut server = frame(0x02, success);
+    server.extend(frame(0x19, serialize("\"bye\"").unwrap()));
+    let DATA = vec![
+        Data::new(false, client, None),
+        Data::new(true, server, None),
+        // sent before the client read the disconnect
+        Data::new(false, frame(0x10, serialize(42i64).unwrap()), None),
+    ];
+
+    let mut inspector = Inspector::new(Options {
+        mode: Mode::Records,
+        ..Options::default()
+    });
+    do_all_data(&DATA, &mut inspector).unwrap();
+    let records: Vec<_> = inspector
+        .records
+        .iter()
+        .map(|record| (record.state, record.name))
+        .collect();
+    dbg!(&records);
+    assert_eq!(records.len(), 5);
+    assert_eq!(records[4], (State::Play, Some("KeepAlive")));
+    assert_eq!(inspector.records[4].fields, Some(serde_json::json!(42)));
+}
+
+// helpers for the tests that talk to the proxy over tcp
+
+fn send(
+    stream: &mut std::net::TcpStream,
+    encoder: &mut digmake::codec::Encoder,
+    packet_id: i32,
+    body: Vec<u8>,
+) {
+    use std::io::Write;
+
+    let mut frame = digmake::se::serialize(VarInt(packet_id)).unwrap();
+    frame.extend(body);
+    stream.write_all(&encoder.encode(&frame).unwrap()).unwrap();
+}
+
+fn receive(
+    stream: &mut std::net::TcpStream,
+    decoder: &mut digmake::codec::Decoder,
+) -> (i32, Vec<u8>) {
+    use std::io::Read;
+
+    let mut buffer = [0; 1024];
+    loop {
+        if let Some(frame) = decoder.next_frame().unwrap() {
+            let (packet_id, body) = digmake::codec::split_packet_id(&frame).unwrap();
+            return (packet_id, body.to_vec());
+        }
+        let len = stream.read(&mut buffer).unwrap();
+        assert!(len > 0);
+        decoder.push(&buffer[..len]);
+    }
+}
+
+// an address nothing is listening on, for a server to be started on
+fn free_address() -> std::net::SocketAddr {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap()
+}
+
+// connects once the server has started
+fn connect(address: std::net::SocketAddr) -> std::net::TcpStream {
+    loop {
+        match std::net::TcpStream::connect(address) {
+            Ok(stream) => return stream,
+            Err(_) => std::thread::sleep(std::time::Duration::from_millis(10)),
+        }
+    }
+}
+
+// starts a proxy to upstream on a free port, and connects to it
+fn connect_through_proxy(
+    upstream: &std::net::TcpListener,
+    log_dir: Option<std::path::PathBuf>,
+    hooks: Vec<digmake::proxy::HookFactory>,
+) -> std::net::TcpStream {
+    use digmake::proxy::{run, ProxyOptions};
+
+    let listen = free_address();
+    let options = ProxyOptions {
+        listen: listen.to_string(),
+        upstream: upstream.local_addr().unwrap().to_string(),
+        log_dir,
+        inspector: None,
+        hooks,
+    };
+    std::thread::spawn(move || run(options).unwrap());
+
+    connect(listen)
+}
+
+pub fn test_proxy() {
+    use digmake::capture::CaptureReader;
+    use digmake::codec::{Decoder, Encoder};
+    use digmake::logic::State;
+    use digmake::se::{from_bytes, serialize};
+    use rsa::pkcs8::{DecodePublicKey, EncodePublicKey};
+    use rsa::{Pkcs1v15Encrypt, RsaPrivateKey, RsaPublicKey};
+    use std::net::TcpListener;
+
+    let upstream = TcpListener::bind("127.0.0.1:0").unwrap();
+    let log_dir = std::env::temp_dir().join(format!(
+        "digmake-proxy-{}",
+        upstream.local_addr().unwrap().port()
+    ));
+    let mut stream = connect_through_proxy(&upstream, Some(log_dir.clone()), Vec::new());
+
+    // an online mode server
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = upstream.accept().unwrap();
+        let (mut decoder, mut encoder) = (Decoder::new(), Encoder::new());
+        assert_eq!(receive(&mut stream, &mut decoder).0, 0x00);
+        assert_eq!(receive(&mut stream, &mut decoder).0, 0x00);
+
+        let key = RsaPrivateKey::new(&mut rand::rngs::OsRng, 1024).unwrap();
+        let public_key = RsaPublicKey::from(&key).to_public_key_der().unwrap();
+        let request = ("", public_key.as_bytes(), vec![1u8, 2, 3, 4]);
+        send(&mut stream, &mut encoder, 0x01, serialize(request).unwrap());
+
+        let (packet_id, body) = receive(&mut stream, &mut decoder);
+        assert_eq!(packet_id, 0x01);
+        let (secret, token): (Vec<u8>, Vec<u8>) = from_bytes(&body).unwrap();
+        let secret = key.decrypt(Pkcs1v15Encrypt, &secret).unwrap();
+        assert_eq!(
+            key.decrypt(Pkcs1v15Encrypt, &token).unwrap(),
+            vec![1, 2, 3, 4]
+        );
+        decoder.enable_encryption(&secret).unwrap();
+        encoder.enable_encryption(&secret).unwrap();
+
+        send(
+            &mut stream,
+            &mut encoder,
+            0x03,
+            serialize(VarInt(16)).unwrap(),
+        );
+        decoder.set_compression(16);
+        encoder.set_compression(16);
+        let success = (uuid::Uuid::nil(), "digmake");
+        send(&mut stream, &mut encoder, 0x02, serialize(success).unwrap());
+        send(&mut stream, &mut encoder, 0x1F, serialize(42i64).unwrap());
+
+        let (packet_id, body) = receive(&mut stream, &mut decoder);
+        assert_eq!((packet_id, from_bytes::<i64>(&body).unwrap()), (0x10, 42));
+        secret
+    });
+
+    let (mut decoder, mut encoder) = (Decoder::new(), Encoder::new());
+    let handshake = (VarInt(754), "localhost", 25565u16, VarInt(2));
+    send(
+        &mut stream,
+        &mut encoder,
+        0x00,
+        serialize(handshake).unwrap(),
+    );
+    send(
+        &mut stream,
+        &mut encoder,
+        0x00,
+        serialize("digmake").unwrap(),
+    );
+
+    // the client gets the proxy's key, not the server's
+    let (packet_id, body) = receive(&mut stream, &mut decoder);
+    assert_eq!(packet_id, 0x01);
+    let (_, public_key, token): (String, Vec<u8>, Vec<u8>) = from_bytes(&body).unwrap();
+    let key = RsaPublicKey::from_public_key_der(&public_key).unwrap();
+    let secret = vec![5u8; 16];
+    let mut rng = rand::rngs::OsRng;
+    let response = (
+        key.encrypt(&mut rng, Pkcs1v15Encrypt, &secret).unwrap(),
+        key.encrypt(&mut rng, Pkcs1v15Encrypt, &token).unwrap(),
+    );
+    send(
+        &mut stream,
+        &mut encoder,
+        0x01,
+        serialize(response).unwrap(),
+    );
+    decoder.enable_encryption(&secret).unwrap();
+    encoder.enable_encryption(&secret).unwrap();
+
+    let (packet_id, body) = receive(&mut stream, &mut decoder);
+    assert_eq!(packet_id, 0x03);
+    decoder.set_compression(16);
+    encoder.set_compression(16);
+    assert_eq!(from_bytes::<VarInt>(&body).unwrap(), VarInt(16));
+    assert_eq!(receive(&mut stream, &mut decoder).0, 0x02);
+    let (packet_id, body) = receive(&mut stream, &mut decoder);
+    assert_eq!((packet_id, from_bytes::<i64>(&body).unwrap()), (0x1F, 42));
+    send(&mut stream, &mut encoder, 0x10, serialize(42i64).unwrap());
+
+    assert_eq!(server.join().unwrap(), secret);
+    drop(stream);
+
+    // every packet is in the capture before the proxy passes it on, and the secret is written as
+    // soon as encryption starts, so both are complete once the server has the last packet
+    let mut paths: Vec<_> = std::fs::read_dir(&log_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    paths.sort();
+    dbg!(&paths);
+    assert_eq!(paths.len(), 2);
+    let DATA = std::fs::read(&paths[0]).unwrap();
+    let records: Vec<_> = CaptureReader::new(&DATA[..])
+        .unwrap()
+        .map(|record| record.unwrap())
+        .collect();
+    let kinds: Vec<_> = records
+        .iter()
+        .map(|record| (record.state, record.packet_id().unwrap()))
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            (State::Handshake, 0x00),
+            (State::Login, 0x00),
+            (State::Login, 0x01),
+            (State::Login, 0x01),
+            (State::Login, 0x03),
+            (State::Login, 0x02),
+            (State::Play, 0x1F),
+            (State::Play, 0x10),
+        ]
+    );
+    assert_eq!(
+        std::fs::read_to_string(&paths[1]).unwrap(),
+        hex::encode(&secret)
+    );
+    std::fs::remove_dir_all(&log_dir).unwrap();
+}
+
+pub fn test_proxy_hooks() {
+    use digmake::codec::{Decoder, Encoder};
+    use digmake::logic::{PacketDirection, State};
+    use digmake::proxy::{Action, ClientboundPacket, Hook, HookFactory, Packet};
+    use digmake::se::{from_bytes, serialize};
+    use std::net::TcpListener;
+    use std::time::{Duration, Instant};
+
+    // drops the first keep alive, and answers the second one for the client after changing it
+    struct KeepAlives;
+
+    impl Hook for KeepAlives {
+        fn on_clientbound(&mut self, packet: &mut ClientboundPacket) -> Action {
+            if packet.name() != Some("KeepAlive") {
+                return Action::Forward;
+            }
+            match packet.read::<i64>().unwrap() {
+                1 => Action::Drop,
+                _ => {
+                    packet.write(&20i64).unwrap();
+                    let answer =
+                        Packet::new(PacketDirection::Serverbound, State::Play, 0x10, &99i64);
+                    Action::Inject(vec![answer.unwrap()])
+                }
+            }
+        }
+    }
+
+    // runs after KeepAlives, so it sees the changed packet. Only keep alives are held back, the
+    // login goes through right away
+    struct Slow;
+
+    impl Hook for Slow {
+        fn on_clientbound(&mut self, packet: &mut ClientboundPacket) -> Action {
+            if packet.name() != Some("KeepAlive") {
+                return Action::Forward;
+            }
+            assert_eq!(packet.read::<i64>().unwrap(), 20);
+            Action::Delay(Duration::from_millis(200))
+        }
+    }
+
+    let upstream = TcpListener::bind("127.0.0.1:0").unwrap();
+    let hooks: Vec<HookFactory> = vec![
+        Box::new(|| Box::new(KeepAlives)),
+        Box::new(|| Box::new(Slow)),
+    ];
+    let mut stream = connect_through_proxy(&upstream, None, hooks);
+
+    // an offline mode server
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = upstream.accept().unwrap();
+        let (mut decoder, mut encoder) = (Decoder::new(), Encoder::new());
+        assert_eq!(receive(&mut stream, &mut decoder).0, 0x00);
+        assert_eq!(receive(&mut stream, &mut decoder).0, 0x00);
+        let success = (uuid::Uuid::nil(), "digmake");
+        send(&mut stream, &mut encoder, 0x02, serialize(success).unwrap());
+        send(&mut stream, &mut encoder, 0x1F, serialize(1i64).unwrap());
+        send(&mut stream, &mut encoder, 0x1F, serialize(2i64).unwrap());
+
+        // the answer from the hook, and then the one from the client
+        let (packet_id, body) = receive(&mut stream, &mut decoder);
+        assert_eq!((packet_id, from_bytes::<i64>(&body).unwrap()), (0x10, 99));
+        let (packet_id, body) = receive(&mut stream, &mut decoder);
+        assert_eq!((packet_id, from_bytes::<i64>(&body).unwrap()), (0x10, 20));
+    });
+
+    let (mut decoder, mut encoder) = (Decoder::new(), Encoder::new());
+    let handshake = (VarInt(754), "localhost", 25565u16, VarInt(2));
+    send(
+        &mut stream,
+        &mut encoder,
+        0x00,
+        serialize(handshake).unwrap(),
+    );
+    send(
+        &mut stream,
+        &mut encoder,
+        0x00,
+        serialize("digmake").unwrap(),
+    );
+    let start = Instant::now();
+    assert_eq!(receive(&mut stream, &mut decoder).0, 0x02);
+    assert!(start.elapsed() < Duration::from_millis(200));
+
+    let (packet_id, body) = receive(&mut stream, &mut decoder);
+    let DATA: i64 = from_bytes(&body).unwrap();
+    dbg!(start.elapsed());
+    assert_eq!((packet_id, DATA), (0x1F, 20));
+    assert!(start.elapsed() >= Duration::from_millis(200));
+    send(&mut stream, &mut encoder, 0x10, serialize(DATA).unwrap());
+
+    server.join().unwrap();
+}
+
+pub fn test_replay() {
+    use digmake::capture::{CaptureWriter, Record};
+    use digmake::codec::{Decoder, Encoder};
+    use digmake::logic::{PacketDirection, State};
+    use digmake::replay::{run, Recording, ReplayOptions};
+    use digmake::se::uuid::offline_player_uuid;
+    use digmake::se::{from_bytes, serialize};
+    use std::time::{Duration, Instant};
+
+    let record = |micros, direction, state, packet_id: i32, body: Vec<u8>| {
+        let mut frame = serialize(VarInt(packet_id)).unwrap();
+        frame.extend(body);
+        Record {
+            micros,
+            direction,
+            state,
+            frame,
+        }
+    };
+    let mut writer = CaptureWriter::new(Vec::new(), Some(754)).unwrap();
+    for record in &[
+        record(
+            0,
+            PacketDirection::Clientbound,
+            State::Login,
+            0x03,
+            vec![0x00],
+        ),
+        record(
+            1_000,
+            PacketDirection::Clientbound,
+            State::Play,
+            0x1F,
+            serialize(7i64).unwrap(),
+        ),
+        // out of order, it is sent right away
+        record(
+            500,
+            PacketDirection::Clientbound,
+            State::Play,
+            0x1F,
+            serialize(6i64).unwrap(),
+        ),
+        record(
+            2_000,
+            PacketDirection::Serverbound,
+            State::Play,
+            0x10,
+            serialize(7i64).unwrap(),
+        ),
+        record(
+            151_000,
+            PacketDirection::Clientbound,
+            State::Play,
+            0x1F,
+            serialize(8i64).unwrap(),
+        ),
+    ] {
+        writer.write_record(record).unwrap();
+    }
+    let DATA = writer.into_inner();
+
+    let recording = Recording::read(&DATA[..]).unwrap();
+    assert_eq!(recording.protocol_version, Some(754));
+    assert_eq!(recording.packets.len(), 3);
+    let listen = free_address();
+    let options = ReplayOptions {
+        listen: listen.to_string(),
+        log_input: true,
+    };
+    std::thread::spawn(move || run(recording, options).unwrap());
+
+    // the server list ping
+    let mut stream = connect(listen);
+    let (mut decoder, mut encoder) = (Decoder::new(), Encoder::new());
+    let handshake = (VarInt(754), "localhost", 25565u16, VarInt(1));
+    send(
+        &mut stream,
+        &mut encoder,
+        0x00,
+        serialize(handshake).unwrap(),
+    );
+    send(&mut stream, &mut encoder, 0x00, Vec::new());
+    let (packet_id, body) = receive(&mut stream, &mut decoder);
+    assert_eq!(packet_id, 0x00);
+    let response: digmake::chat::Json<digmake::chat::StatusResponse> = from_bytes(&body).unwrap();
+    assert_eq!(response.0.version.protocol, 754);
+    send(&mut stream, &mut encoder, 0x01, serialize(5i64).unwrap());
+    assert_eq!(
+        receive(&mut stream, &mut decoder),
+        (0x01, serialize(5i64).unwrap())
+    );
+
+    // a client with the wrong version is turned away
+    let mut stream = connect(listen);
+    let (mut decoder, mut encoder) = (Decoder::new(), Encoder::new());
+    let handshake = (VarInt(753), "localhost", 25565u16, VarInt(2));
+    send(
+        &mut stream,
+        &mut encoder,
+        0x00,
+        serialize(handshake).unwrap(),
+    );
+    send(
+        &mut stream,
+        &mut encoder,
+        0x00,
+        serialize("digmake").unwrap(),
+    );
+    assert_eq!(receive(&mut stream, &mut decoder).0, 0x00);
+
+    let mut stream = connect(listen);
+    let (mut decoder, mut encoder) = (Decoder::new(), Encoder::new());
+    let handshake = (VarInt(754), "localhost", 25565u16, VarInt(2));
+    send(
+        &mut stream,
+        &mut encoder,
+        0x00,
+        serialize(handshake).unwrap(),
+    );
+    send(
+        &mut stream,
+        &mut encoder,
+        0x00,
+        serialize("digmake").unwrap(),
+    );
+    let (packet_id, body) = receive(&mut stream, &mut decoder);
+    assert_eq!(packet_id, 0x02);
+    let (uuid, username): (digmake::se::Uuid, String) = from_bytes(&body).unwrap();
+    assert_eq!(
+        (uuid.0, username.as_str()),
+        (offline_player_uuid("digmake"), "digmake")
+    );
+
+    // the play packets come with the recorded time between them
+    let (packet_id, body) = receive(&mut stream, &mut decoder);
+    let start = Instant::now();
+    assert_eq!((packet_id, from_bytes::<i64>(&body).unwrap()), (0x1F, 7));
+    send(&mut stream, &mut encoder, 0x10, body);
+    let (packet_id, body) = receive(&mut stream, &mut decoder);
+    assert_eq!((packet_id, from_bytes::<i64>(&body).unwrap()), (0x1F, 6));
+    let (packet_id, body) = receive(&mut stream, &mut decoder);
+    dbg!(start.elapsed());
+    assert_eq!((packet_id, from_bytes::<i64>(&body).unwrap()), (0x1F, 8));
+    assert!(start.elapsed() >= Duration::from_millis(140));
+}
+
+pub fn test_client() {
+    use digmake::chat::{Json, TextComponent};
+    use digmake::client::{Client, Event, Location};
+    use digmake::codec::{Decoder, Encoder};
+    use digmake::se::uuid::offline_player_uuid;
+    use digmake::se::{from_bytes, serialize};
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    let listen = free_address();
+    let listener = TcpListener::bind(listen).unwrap();
+    let uuid = offline_player_uuid("bot");
+
+    // a server that logs the client in with compression, and then checks what it answers
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let (mut decoder, mut encoder) = (Decoder::new(), Encoder::new());
+
+        let (packet_id, body) = receive(&mut stream, &mut decoder);
+        assert_eq!(packet_id, 0x00);
+        let (protocol_version, _, _, next_state): (VarInt, String, u16, VarInt) =
+            from_bytes(&body).unwrap();
+        assert_eq!((protocol_version.0, next_state.0), (754, 2));
+        let (packet_id, body) = receive(&mut stream, &mut decoder);
+        assert_eq!(packet_id, 0x00);
+        assert_eq!(from_bytes::<String>(&body).unwrap(), "bot");
+
+        send(
+            &mut stream,
+            &mut encoder,
+            0x03,
+            serialize(VarInt(16)).unwrap(),
+        );
+        decoder.set_compression(16);
+        encoder.set_compression(16);
+        let request = (VarInt(7), "velocity:player_info", 1u8);
+        send(&mut stream, &mut encoder, 0x04, serialize(request).unwrap());
+        let (packet_id, body) = receive(&mut stream, &mut decoder);
+        assert_eq!(packet_id, 0x02);
+        assert_eq!(
+            from_bytes::<(VarInt, bool)>(&body).unwrap(),
+            (VarInt(7), false)
+        );
+        send(
+            &mut stream,
+            &mut encoder,
+            0x02,
+            serialize((uuid, "bot")).unwrap(),
+        );
+
+        send(&mut stream, &mut encoder, 0x24, serialize(42i32).unwrap());
+        let teleport = (1.0f64, 64.0f64, -1.0f64, 90.0f32, 0.0f32, 0u8, VarInt(3));
+        send(
+            &mut stream,
+            &mut encoder,
+            0x34,
+            serialize(teleport).unwrap(),
+        );
+        let (packet_id, body) = receive(&mut stream, &mut decoder);
+        assert_eq!(packet_id, 0x00);
+        assert_eq!(from_bytes::<VarInt>(&body).unwrap().0, 3);
+        let (packet_id, body) = receive(&mut stream, &mut decoder);
+        assert_eq!(packet_id, 0x13);
+        let position: (f64, f64, f64, f32, f32, bool) = from_bytes(&body).unwrap();
+        assert_eq!(position, (1.0, 64.0, -1.0, 90.0, 0.0, false));
+
+        send(&mut stream, &mut encoder, 0x1F, serialize(1234i64).unwrap());
+        let (packet_id, body) = receive(&mut stream, &mut decoder);
+        assert_eq!(packet_id, 0x10);
+        assert_eq!(from_bytes::<i64>(&body).unwrap(), 1234);
+
+        // only x and yaw are relative
+        let teleport = (2.0f64, 70.0f64, 5.0f64, 10.0f32, 45.0f32, 0x09u8, VarInt(4));
+        send(
+            &mut stream,
+            &mut encoder,
+            0x34,
+            serialize(teleport).unwrap(),
+        );
+        let message = (Json(TextComponent::text("hello bot")), 0u8, uuid);
+        send(&mut stream, &mut encoder, 0x0E, serialize(message).unwrap());
+        let latency = (VarInt(2), VarInt(1), uuid, VarInt(25));
+        send(&mut stream, &mut encoder, 0x32, serialize(latency).unwrap());
+        send(
+            &mut stream,
+            &mut encoder,
+            0x4E,
+            serialize((1i64, 2i64)).unwrap(),
+        );
+
+        let (packet_id, _) = receive(&mut stream, &mut decoder);
+        assert_eq!(packet_id, 0x00);
+        let (packet_id, _) = receive(&mut stream, &mut decoder);
+        assert_eq!(packet_id, 0x13);
+
+        // what the test sends
+        let (packet_id, body) = receive(&mut stream, &mut decoder);
+        assert_eq!(packet_id, 0x03);
+        assert_eq!(from_bytes::<String>(&body).unwrap(), "hello server");
+        let (packet_id, body) = receive(&mut stream, &mut decoder);
+        assert_eq!(packet_id, 0x13);
+        let position: (f64, f64, f64, f32, f32, bool) = from_bytes(&body).unwrap();
+        assert_eq!(position, (3.0, 71.0, 5.0, 100.0, 45.0, true));
+        let (packet_id, body) = receive(&mut stream, &mut decoder);
+        assert_eq!(packet_id, 0x03);
+        assert_eq!(from_bytes::<String>(&body).unwrap(), "é".repeat(256));
+
+        // more than fit while the test is not reading the events, keep alives are still answered
+        for i in 0..2000i64 {
+            send(&mut stream, &mut encoder, 0x4E, serialize((i, i)).unwrap());
+        }
+        send(&mut stream, &mut encoder, 0x1F, serialize(5678i64).unwrap());
+        let (packet_id, body) = receive(&mut stream, &mut decoder);
+        assert_eq!(packet_id, 0x10);
+        assert_eq!(from_bytes::<i64>(&body).unwrap(), 5678);
+
+        let reason = Json(TextComponent::text("bye"));
+        send(&mut stream, &mut encoder, 0x19, serialize(reason).unwrap());
+    });
+
+    let client = Client::connect(listen, "bot").unwrap();
+    assert_eq!(client.username, "bot");
+    assert_eq!(client.uuid, uuid);
+
+    let next_event = || {
+        let event = client.events().recv_timeout(Duration::from_secs(5));
+        dbg!(event.unwrap())
+    };
+    let DATA: Vec<Event> = (0..8).map(|_| next_event()).collect();
+    assert_eq!(DATA[0], Event::JoinGame { entity_id: 42 });
+    assert_eq!(
+        DATA[1],
+        Event::Teleported(Location {
+            x: 1.0,
+            y: 64.0,
+            z: -1.0,
+            yaw: 90.0,
+            pitch: 0.0,
+        })
+    );
+    assert_eq!(DATA[2], Event::KeepAlive(1234));
+    // the teleport after the answer
+    assert!(matches!(DATA[3], Event::KeepAliveRoundTrip(_)));
+    let location = Location {
+        x: 3.0,
+        y: 70.0,
+        z: 5.0,
+        yaw: 100.0,
+        pitch: 45.0,
+    };
+    assert_eq!(DATA[4], Event::Teleported(location));
+    assert_eq!(client.location(), Some(location));
+    assert_eq!(
+        DATA[5],
+        Event::Chat {
+            message: Box::new(TextComponent::text("hello bot")),
+            position: 0,
+            sender: uuid,
+        }
+    );
+    assert_eq!(DATA[6], Event::Latency(Duration::from_millis(25)));
+    assert_eq!(
+        DATA[7],
+        Event::Packet(0x4E, serialize((1i64, 2i64)).unwrap())
+    );
+
+    // the rest of the events come after the server has seen these
+    client.chat("hello server").unwrap();
+    client.move_by(0.0, 1.0, 0.0, true).unwrap();
+    assert!(client.chat(&"a".repeat(257)).is_err());
+    client.chat(&"é".repeat(256)).unwrap();
+
+    // the packets that did not fit were dropped, but not the keep alive
+    server.join().unwrap();
+    let mut packets = 0;
+    let event = loop {
+        match next_event() {
+            Event::Packet(0x4E, _) => packets += 1,
+            event => break event,
+        }
+    };
+    assert_eq!(packets, 1024);
+    assert_eq!(event, Event::KeepAlive(5678));
+    assert!(matches!(next_event(), Event::KeepAliveRoundTrip(_)));
+    assert_eq!(next_event(), Event::Disconnected(String::from("bye")));
+    assert!(!client.is_connected());
+
+    let stats = client.stats();
+    assert_eq!(
+        stats.packets[&(digmake::logic::State::Play, true, 0x34)].count,
+        2
+    );
+    assert_eq!(
+        stats.packets[&(digmake::logic::State::Play, false, 0x13)].count,
+        3
+    );
+}
+
+pub fn test_loadtest() {
+    use digmake::chat::{Json, TextComponent};
+    use digmake::codec::{Decoder, Encoder};
+    use digmake::loadtest::{percentile, run, LoadTestOptions};
+    use digmake::logic::State;
+    use digmake::se::uuid::offline_player_uuid;
+    use digmake::se::{from_bytes, serialize};
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    let listen = free_address();
+    let listener = TcpListener::bind(listen).unwrap();
+
+    // a server that is full after two players, and kicks anyone who chats
+    std::thread::spawn(move || {
+        for stream in listener.incoming().take(3) {
+            let mut stream = stream.unwrap();
+            std::thread::spawn(move || {
+                let (mut decoder, mut encoder) = (Decoder::new(), Encoder::new());
+                receive(&mut stream, &mut decoder);
+                let (_, body) = receive(&mut stream, &mut decoder);
+                let username: String = from_bytes(&body).unwrap();
+                if username == "load2" {
+                    let reason = Json(TextComponent::text("the server is full"));
+                    send(&mut stream, &mut encoder, 0x00, serialize(reason).unwrap());
+                    return;
+                }
+
+                let uuid = offline_player_uuid(&username);
+                send(
+                    &mut stream,
+                    &mut encoder,
+                    0x02,
+                    serialize((uuid, &username)).unwrap(),
+                );
+                let teleport = (0.5f64, 64.0f64, 0.5f64, 0.0f32, 0.0f32, 0u8, VarInt(1));
+                send(
+                    &mut stream,
+                    &mut encoder,
+                    0x34,
+                    serialize(teleport).unwrap(),
+                );
+                let latency = (VarInt(2), VarInt(1), uuid, VarInt(15));
+                send(&mut stream, &mut encoder, 0x32, serialize(latency).unwrap());
+                // each answered 50ms and then 150ms before the next packet, ahead of the first chat
+                for (id, wait) in [(1i64, 50), (2, 150), (3, 0)].iter() {
+                    send(&mut stream, &mut encoder, 0x1F, serialize(id).unwrap());
+                    while receive(&mut stream, &mut decoder).0 != 0x10 {}
+                    std::thread::sleep(Duration::from_millis(*wait));
+                }
+
+                while receive(&mut stream, &mut decoder).0 != 0x03 {}
+                let reason = Json(TextComponent::text("kicked for chatting"));
+                send(&mut stream, &mut encoder, 0x19, serialize(reason).unwrap());
+            });
+        }
+    });
+
+    let options = LoadTestOptions {
+        address: listen.to_string(),
+        bots: 3,
+        name_prefix: String::from("load"),
+        connect_interval: Duration::from_millis(20),
+        duration: Duration::from_secs(10),
+        move_rate: 20.0,
+        chat_rate: 4.0,
+    };
+    let DATA = run(&options);
+    DATA.print();
+
+    assert_eq!(DATA.bots, 3);
+    assert_eq!(DATA.connect_times.len(), 2);
+    assert_eq!(DATA.round_trips.len(), 6);
+    assert!(DATA.round_trips[2] >= Duration::from_millis(45));
+    assert!(DATA.round_trips[4] >= Duration::from_millis(140));
+    assert_eq!(DATA.pings, vec![Duration::from_millis(15); 2]);
+    assert_eq!(DATA.keep_alive_jitter.len(), 2);
+    for jitter in &DATA.keep_alive_jitter {
+        assert!(*jitter > Duration::from_millis(70) && *jitter < Duration::from_millis(130));
+    }
+    assert_eq!(DATA.disconnect_reasons.len(), 2);
+    assert_eq!(DATA.disconnect_reasons["kicked for chatting"], 2);
+    assert_eq!(
+        DATA.disconnect_reasons
+            ["could not log in: disconnected while logging in: the server is full"],
+        1
+    );
+    assert_eq!(DATA.stats.packets[&(State::Play, false, 0x03)].count, 2);
+    assert_eq!(DATA.stats.packets[&(State::Play, true, 0x34)].count, 2);
+    // the answers to the teleports, and then some walking before the first chat
+    assert!(DATA.stats.packets[&(State::Play, false, 0x13)].count > 2);
+
+    let values: Vec<Duration> = (1..=10).map(Duration::from_secs).collect();
+    assert_eq!(percentile(&values, 0.0), Some(Duration::from_secs(1)));
+    assert_eq!(percentile(&values, 50.0), Some(Duration::from_secs(5)));
+    assert_eq!(percentile(&values, 99.0), Some(Duration::from_secs(10)));
+    assert_eq!(percentile(&[], 50.0), None);
+}